@@ -1,18 +1,27 @@
-use zwl_gs::bit_decoder::ZwlBitDecoder;
 use zwl_gs::bit_encoder::ZwlBitEncoder;
 use zwl_gs::dictionary::FilledBehaviour;
-use zwl_gs::like_u12::LikeU12;
-use zwl_gs::like_u16::LikeU16;
+use zwl_gs::auto_decoder::DecodeOptions;
+use zwl_gs::members::ZwlMemberDecoder;
+use zwl_gs::preset::PresetDictionary;
+use zwl_gs::header::ZwlHeader;
+use zwl_gs::code_width::{MAX_CODE_WIDTH, MIN_CODE_WIDTH};
+use zwl_gs::unix_compress::{ZDecoder, ZEncoder, Z_MAGIC, Z_MAX_BITS};
+use zwl_gs::stats::{CodingStats, Counter};
+use zwl_gs::archive::{ArchiveEntry, EntryKind, ZwlArchiveReader, ZwlArchiveWriter};
+use zwl_gs::with_code_width;
 
 use clap::Parser;
 use serde::Serialize;
 use dialoguer::{Confirm, Editor};
-use zwl_gs::like_u32::LikeU32;
-use zwl_gs::like_u64::LikeU64;
 
-use std::fs::File;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Path that stands for standard input or output
+const STDIO: &str = "-";
 
 #[cfg_attr(debug_assertions, derive(Debug))]
 #[derive(
@@ -22,7 +31,12 @@ use std::path::PathBuf;
 enum Mode{
     #[default]
     Encode,
-    Decode
+    Decode,
+    Train,
+    Pack,
+    Unpack,
+    List,
+    Test
 }
 
 
@@ -31,12 +45,30 @@ enum Mode{
     clap::ValueEnum, Clone, Default, Serialize
 )]
 #[serde(rename_all = "kebab-case")]
-enum Encoding{
+enum Format{
     #[default]
-    U12,
-    U16,
-    U32,
-    U64
+    Zwl,
+    Z
+}
+
+fn parse_encoding(value: &str) -> Result<u8, String>{
+    let bits = value.strip_prefix(['u', 'U']).unwrap_or(value);
+    match bits.parse::<u8>(){
+        Ok(bits) if (MIN_CODE_WIDTH..=MAX_CODE_WIDTH).contains(&bits) => Ok(bits),
+        _ => Err(format!("expected an index width from u{MIN_CODE_WIDTH} to u{MAX_CODE_WIDTH}")),
+    }
+}
+
+
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(
+    clap::ValueEnum, Clone, Default, Serialize
+)]
+#[serde(rename_all = "kebab-case")]
+enum StatsFormat{
+    #[default]
+    Text,
+    Json
 }
 
 
@@ -48,7 +80,9 @@ enum Encoding{
 enum FilledOption{
     #[default]
     Clear,
-    Freeze
+    Freeze,
+    Adaptive,
+    Lru
 }
 
 impl From<FilledOption> for FilledBehaviour{
@@ -56,6 +90,8 @@ impl From<FilledOption> for FilledBehaviour{
         match val{
             FilledOption::Clear => Self::Clear,
             FilledOption::Freeze => Self::Freeze,
+            FilledOption::Adaptive => Self::Adaptive,
+            FilledOption::Lru => Self::Lru,
         }
     }
 }
@@ -65,53 +101,243 @@ impl From<FilledOption> for FilledBehaviour{
 #[derive(Parser)]
 #[command(version, about)]
 struct Cli {
-    input_file: PathBuf,
-    output_file: Option<PathBuf>,
+    #[arg(required = true, help = "Files or directories to read, - for standard input. When encoding or decoding exactly two paths, the second is the output file if it does not exist yet or ends in .zwl, unless --batch, --recursive, --yes or --delete is given")]
+    input_files: Vec<PathBuf>,
+    #[arg(long, help = "File to write for a single input, archive to pack into or directory to unpack into, - for standard output. Standard input is written to standard output by default")]
+    output: Option<PathBuf>,
+    #[arg(long, short, default_value_t = false, help = "Compress or decompress the files in directories")]
+    recursive: bool,
+    #[arg(long, default_value_t = false, help = "Treat every path as an input, even when there are two")]
+    batch: bool,
+    #[arg(long, default_value_t = false, help = "Delete the originals once they are processed, they are kept by default like with gzip -k")]
+    delete: bool,
     #[arg(long, short, default_value_t = Mode::Encode, value_enum)]
     mode: Mode,
     #[arg(long, short, default_value_t = false)]
     overwrite: bool,
+    #[arg(long, short = 'c', default_value_t = false, help = "Write to standard output")]
+    stdout: bool,
+    #[arg(long, short = 'y', visible_alias = "no-interactive", default_value_t = false, help = "Never prompt, accept the derived output name and replace existing files")]
+    yes: bool,
     #[arg(long, short, default_value_t = FilledOption::Clear, value_enum, help = "Filled behavior of dictionary used in encoding mode")]
     filled: FilledOption,
-    #[arg(long, short, default_value_t = Encoding::U12, value_enum, help = "Ecnoding used in encoding mode")]
-    encoding: Encoding
+    #[arg(long, short, value_parser = parse_encoding, help = "Ecnoding used in encoding mode, any index width from u9 to u64 (u12 by default, u16 for .Z)")]
+    encoding: Option<u8>,
+    #[arg(long, default_value_t = Format::Zwl, value_enum, help = "Container format used in encoding mode, decoding detects it")]
+    format: Format,
+    #[arg(long, help = "Preset dictionary made in train mode, primes the dictionary when encoding and decoding")]
+    dict: Option<PathBuf>,
+    #[arg(long, help = "Compress independent blocks of this many bytes in parallel, zwl format only")]
+    block_size: Option<u32>,
+    #[arg(long, help = "Threads used for block mode, all cores by default")]
+    threads: Option<usize>,
+    #[arg(long, default_value_t = false, help = "Write a block index for random access, implies block mode")]
+    seekable: bool,
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "text", help = "Print sizes, codes and speed of every file to standard error, --stats=json prints a JSON object per line")]
+    stats: Option<StatsFormat>,
+    #[arg(long, short, default_value_t = false, help = "Same as --stats=text")]
+    verbose: bool
+}
+
+// Report of one file for --stats, sizes are counted on the files and the codes come from the zwl coders
+#[derive(Serialize)]
+struct StatsReport{
+    file: String,
+    mode: Mode,
+    input_bytes: u64,
+    output_bytes: u64,
+    // Compressed size divided by original size
+    ratio: f64,
+    // Compressed bits per original byte
+    bits_per_byte: f64,
+    codes: Option<u64>,
+    width_escapes: Option<u64>,
+    clears: Option<u64>,
+    dictionary_len: Option<usize>,
+    seconds: f64,
+    // Original megabytes (10^6 bytes) per second
+    throughput_mb_s: f64,
+}
+
+impl StatsReport{
+    fn new(cli: &Cli, input_path: &Path, input_bytes: u64, output_bytes: u64, stats: Option<CodingStats>, seconds: f64) -> Self{
+        let (original, compressed) = match cli.mode{
+            Mode::Decode | Mode::Test => (output_bytes, input_bytes),
+            _ => (input_bytes, output_bytes),
+        };
+        let ratio = if original > 0 { compressed as f64 / original as f64 } else { 0.0 };
+        Self{
+            file: input_path.display().to_string(),
+            mode: cli.mode.clone(),
+            input_bytes,
+            output_bytes,
+            ratio,
+            bits_per_byte: ratio * 8.0,
+            codes: stats.map(|stats| stats.codes),
+            width_escapes: stats.map(|stats| stats.width_escapes),
+            clears: stats.map(|stats| stats.clears),
+            dictionary_len: stats.map(|stats| stats.dictionary_len),
+            seconds,
+            throughput_mb_s: if seconds > 0.0 { original as f64 / seconds / 1e6 } else { 0.0 },
+        }
+    }
+    fn print(&self, format: StatsFormat) -> io::Result<()>{
+        match format{
+            StatsFormat::Json => eprintln!("{}", serde_json::to_string(self).map_err(io::Error::other)?),
+            StatsFormat::Text => {
+                eprintln!("{}: {} -> {} bytes, ratio {:.2}%, {:.3} bits per byte, {:.2} MB/s",
+                    self.file, self.input_bytes, self.output_bytes, self.ratio * 100.0, self.bits_per_byte, self.throughput_mb_s);
+                if let (Some(codes), Some(width_escapes), Some(clears), Some(dictionary_len)) = (self.codes, self.width_escapes, self.clears, self.dictionary_len){
+                    eprintln!("    {codes} codes, {width_escapes} width escapes, {clears} clears, {dictionary_len} dictionary entries");
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
-fn main() -> io::Result<()>{
+impl Cli{
+    // `input output` names the output of a single file, as it did before several inputs were accepted.
+    // An existing second path is only taken as the output when it is a .zwl file and nothing is
+    // replaced or deleted without asking, otherwise both are inputs and --output has to be used
+    fn take_positional_output(&mut self){
+        if self.input_files.len() != 2 || self.output.is_some() || self.batch || self.recursive || self.stdout
+            || self.delete || self.yes || !matches!(self.mode, Mode::Encode | Mode::Decode){
+            return;
+        }
+        let second = &self.input_files[1];
+        if !second.exists() || second.extension().is_some_and(|extension| extension == "zwl"){
+            self.output = self.input_files.pop();
+        }
+    }
+}
+
+fn main() -> ExitCode{
     // TryInto::<Vec<bool>>::try_into(LikeU12::from(10));
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    cli.take_positional_output();
     #[cfg(debug_assertions)]
-    println!("{:?}", cli);
-    let input_path = cli.input_file.clone();
+    eprintln!("{:?}", cli);
+    // An archive is the one output of all its inputs
+    if cli.output.is_some() && cli.input_files.len() > 1 && !matches!(cli.mode, Mode::Pack){
+        eprintln!("--output can only be used with a single input file, or to name the archive in pack mode");
+        return ExitCode::FAILURE;
+    }
+    let preset = match cli.dict.as_deref().map(read_preset).transpose(){
+        Ok(preset) => preset,
+        Err(e) => {
+            eprintln!("{}: {e}", cli.dict.as_deref().unwrap_or(Path::new("")).display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = false;
+    match cli.mode{
+        Mode::Pack => {
+            if let Err(e) = pack(&cli, &cli.input_files, preset.as_ref()){
+                eprintln!("{}: {e}", cli.input_files[0].display());
+                failed = true;
+            }
+        }
+        Mode::Unpack | Mode::List => {
+            for path in &cli.input_files{
+                let result = match cli.mode{
+                    Mode::Unpack => unpack(&cli, path, preset.as_ref()),
+                    _ => list(path, preset.as_ref()),
+                };
+                if let Err(e) = result{
+                    eprintln!("{}: {e}", path.display());
+                    failed = true;
+                }
+            }
+        }
+        _ => failed |= compress_files(&cli, preset.as_ref()),
+    }
+    if failed{
+        ExitCode::FAILURE
+    }
+    else{
+        ExitCode::SUCCESS
+    }
+}
 
-    let output_path = match cli.output_file{
+// Compresses, decompresses or trains on the inputs, returns whether any of them failed
+fn compress_files(cli: &Cli, preset: Option<&PresetDictionary>) -> bool{
+    let mut failed = false;
+    let mut files = vec![];
+    for path in &cli.input_files{
+        collect_inputs(cli, path, true, &mut files, &mut failed);
+    }
+    if let Mode::Train = cli.mode{
+        if let Some(first) = files.first()
+            && let Err(e) = train(cli, first, files.clone()){
+            eprintln!("{}: {e}", first.display());
+            failed = true;
+        }
+    }
+    else{
+        for path in &files{
+            match process(cli, path, preset){
+                Ok(()) if matches!(cli.mode, Mode::Test) => println!("{}: OK", path.display()),
+                Ok(()) => {},
+                Err(e) => {
+                    if matches!(cli.mode, Mode::Test){
+                        eprintln!("{}: FAIL {e}", path.display());
+                    }
+                    else{
+                        eprintln!("{}: {e}", path.display());
+                    }
+                    failed = true;
+                }
+            }
+        }
+    }
+    failed
+}
+
+// Where the result for an input goes, `Skip` when the user declined at a prompt
+enum Target{
+    File(PathBuf),
+    Stdout,
+    Skip,
+}
+
+fn output_target(cli: &Cli, input_path: &Path) -> io::Result<Target>{
+    let to_stdout = cli.stdout || match &cli.output{
+        Some(output) => is_stdio(output),
+        None => is_stdio(input_path),
+    };
+    let output_path = match cli.output.clone(){
+        _ if to_stdout => return Ok(Target::Stdout),
         Some(output) =>{
             output
         }
         None =>{
-            let mut out = input_path.clone();
+            let mut out = input_path.to_path_buf();
             match cli.mode{
-                Mode::Encode => {
-                    let mut new_extension = out.extension().map(|e| e.to_os_string()).unwrap_or_default();
-                    new_extension.push(".zwl");
-                    out.set_extension(new_extension);
-                    out
-                }
-                Mode::Decode => {
+                Mode::Train | Mode::Pack | Mode::Unpack | Mode::List => append_extension(&out, match cli.mode{
+                    Mode::Train => ".zwld",
+                    _ => ".zwla",
+                }),
+                Mode::Encode => append_extension(&out, match cli.format{
+                    Format::Zwl => ".zwl",
+                    Format::Z => ".Z",
+                }),
+                Mode::Decode | Mode::Test => {
                     out.set_extension("");
+                    if !is_unix_compress(input_path)?
+                        && let Some(stored_name) = ZwlHeader::read_from(&mut File::open(input_path)?)?.file_name
+                        && let Some(stored_name) = Path::new(&stored_name).file_name(){
+                        out.set_file_name(stored_name);
+                    }
 
-                    let confirmation = Confirm::new()
-                        .with_prompt(format!("Should the name of new file be {:?}", &out))
-                        .interact()
-                        .unwrap();
-                    if !confirmation{
-                        if let Some(rv) = Editor::new().edit(out.to_str().unwrap()).unwrap() {
+                    if !confirm(cli, format!("Should the name of new file be {:?}", &out))?{
+                        if let Some(rv) = Editor::new().edit(&out.to_string_lossy()).map_err(io::Error::other)? {
                             println!("The file will become:");
                             println!("{}", rv);
                             out = rv.into();
                         } else {
-                            println!("No name for the output file found! Exiting");
-                            return Ok(());
+                            println!("No name for the output file found! Skipping");
+                            return Ok(Target::Skip);
                         }
                     }
                     out
@@ -119,111 +345,469 @@ fn main() -> io::Result<()>{
             }
         }
     };
-    if output_path.exists() && !cli.overwrite{
-        let confirmation = Confirm::new()
-                .with_prompt(format!("File {:?} already exists. Do you want to replace it?", output_path))
-                .interact().unwrap();
-        if !confirmation{
-            println!("Canceled writting into existing file");
-            return Ok(());
-        }
+    if output_path == input_path{
+        return Err(io::Error::other("Output file would replace the input file"));
     }
-    match cli.mode{
+    if output_path.exists() && !cli.overwrite
+        && !confirm(cli, format!("File {:?} already exists. Do you want to replace it?", output_path))?{
+        println!("Canceled writting into existing file");
+        return Ok(Target::Skip);
+    }
+    Ok(Target::File(output_path))
+}
+
+// Trains one preset dictionary on all inputs, named after the first one
+fn train(cli: &Cli, first: &Path, files: Vec<PathBuf>) -> io::Result<()>{
+    let output_path = match output_target(cli, first)?{
+        Target::File(path) => Some(path),
+        Target::Stdout => None,
+        Target::Skip => return Ok(()),
+    };
+    // Half of the dictionary is left for phrases of the compressed data itself
+    let bits = cli.encoding.unwrap_or(12);
+    let max_phrases = (1usize << bits.min(32)).saturating_sub(258) / 2;
+    let input = Concat { files: files.into_iter(), current: Box::new(io::empty()) };
+    let preset = PresetDictionary::train(io::BufReader::new(input), max_phrases)?;
+    preset.write_to(&mut create_output(output_path.as_deref())?)
+}
+
+fn process(cli: &Cli, input_path: &Path, preset: Option<&PresetDictionary>) -> io::Result<()>{
+    let output_path = match cli.mode{
+        // Test mode decodes into nothing, so checking a backup needs no disk space
+        Mode::Test => None,
+        _ => match output_target(cli, input_path)?{
+            Target::File(path) => Some(path),
+            Target::Stdout => None,
+            Target::Skip => return Ok(()),
+        },
+    };
+    let started = Instant::now();
+    let mut input = Counter::new(open_input(input_path)?);
+    let mut output = Counter::new(match cli.mode{
+        Mode::Test => Box::new(io::sink()),
+        _ => create_output(output_path.as_deref())?,
+    });
+    let stats = match cli.mode{
+        Mode::Train | Mode::Pack | Mode::Unpack | Mode::List => unreachable!("archive and train modes are handled on their own"),
         Mode::Encode => {
-            let input = File::open(input_path)?;
-            let output = File::create(output_path)?;
-            match cli.encoding{
-                Encoding::U12 => {
-                    let mut encoder = ZwlBitEncoder::<LikeU12, File>::new(input, cli.filled.into());
-                    encoder.encode(output)?;
-                },
-                Encoding::U16 => {
-                    let mut encoder = ZwlBitEncoder::<LikeU16, File>::new(input, cli.filled.into());
-                    encoder.encode(output)?;
-                },
-                Encoding::U32 => {
-                    let mut encoder = ZwlBitEncoder::<LikeU32, File>::new(input, cli.filled.into());
-                    encoder.encode(output)?;
-                },
-                Encoding::U64 => {
-                    let mut encoder = ZwlBitEncoder::<LikeU64, File>::new(input, cli.filled.into());
-                    encoder.encode(output)?;
-                },
+            let (original_len, file_name) = if is_stdio(input_path){
+                (None, None)
+            }
+            else{
+                (Some(input_path.metadata()?.len()), input_path.file_name().map(|name| name.to_string_lossy().into_owned()))
+            };
+            match cli.format{
+                Format::Zwl => with_code_width!(cli.encoding.unwrap_or(12), W => {
+                    let mut encoder = ZwlBitEncoder::<W, _>::new(&mut input, cli.filled.clone().into());
+                    encoder.original_len = original_len;
+                    encoder.file_name = file_name;
+                    if let Some(preset) = preset{
+                        encoder.prime(preset);
+                    }
+                    encoder.block_size = cli.block_size;
+                    encoder.seekable = cli.seekable;
+                    if let Some(threads) = cli.threads{
+                        encoder.threads = threads;
+                    }
+                    encoder.encode(&mut output)?;
+                    Some(encoder.stats())
+                }, _ => unreachable!("encoding width is validated by the argument parser")),
+                Format::Z => {
+                    ZEncoder::new(&mut input, cli.encoding.unwrap_or(Z_MAX_BITS)).encode(&mut output)?;
+                    None
+                }
             }
         }
-        Mode::Decode => {
-            let input = File::open(input_path)?;
-            let decoder = get_decoder(input)?;
-            let output = File::create(output_path)?;
-            match decoder{
-                ZwlDecoderE::DU12(mut zwl_decoder) => zwl_decoder.decode(output)?,
-                ZwlDecoderE::DU16(mut zwl_decoder) => zwl_decoder.decode(output)?,
-                ZwlDecoderE::DU32(mut zwl_decoder) => zwl_decoder.decode(output)?,
-                ZwlDecoderE::DU64(mut zwl_decoder) => zwl_decoder.decode(output)?,
+        Mode::Decode | Mode::Test => {
+            let (unix_compress, peeked) = peek_unix_compress(&mut input)?;
+            if unix_compress{
+                let mut decoder = ZDecoder::new(peeked)?;
+                decoder.decode(&mut output)?;
+                None
+            }
+            else{
+                let mut decoder = ZwlMemberDecoder::new(peeked, &decode_options(cli, preset))?;
+                decoder.decode(&mut output)?;
+                if decoder.trailing_data && matches!(cli.mode, Mode::Test){
+                    return Err(io::Error::other("Data after the last member"));
+                }
+                Some(decoder.stats())
             }
         }
+    };
+    output.flush()?;
+    if let Some(format) = cli.stats.clone().or(cli.verbose.then_some(StatsFormat::Text)){
+        StatsReport::new(cli, input_path, input.count(), output.count(), stats, started.elapsed().as_secs_f64()).print(format)?;
+    }
+    // Like gzip, originals are only removed once their output was written to a file
+    if cli.delete && output_path.is_some() && !is_stdio(input_path){
+        fs::remove_file(input_path)?;
     }
-    
     Ok(())
 }
 
-
-
-pub enum ZwlDecoderE<I: Read>{
-    DU12(ZwlBitDecoder<LikeU12, I>),
-    DU16(ZwlBitDecoder<LikeU16, I>),
-    DU32(ZwlBitDecoder<LikeU32, I>),
-    DU64(ZwlBitDecoder<LikeU64, I>)
+// Named inputs are always used, files found while walking directories only when they fit the mode
+fn collect_inputs(cli: &Cli, path: &Path, named: bool, files: &mut Vec<PathBuf>, failed: &mut bool){
+    if is_stdio(path) || !path.is_dir(){
+        if named || is_selected(cli, path){
+            files.push(path.to_path_buf());
+        }
+        return;
+    }
+    if !cli.recursive{
+        eprintln!("{}: Is a directory, pass --recursive to compress the files in it", path.display());
+        *failed = true;
+        return;
+    }
+    let entries = fs::read_dir(path).and_then(|entries| entries
+        // Symbolic links are not followed, so walking cannot loop
+        .filter(|entry| !entry.as_ref().is_ok_and(|entry| entry.file_type().is_ok_and(|kind| kind.is_symlink())))
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>());
+    match entries{
+        Ok(mut entries) => {
+            entries.sort();
+            for entry in entries{
+                collect_inputs(cli, &entry, false, files, failed);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            *failed = true;
+        }
+    }
 }
 
-impl<I: Read> From::<ZwlBitDecoder<LikeU12, I>> for ZwlDecoderE<I>{
-    fn from(value: ZwlBitDecoder<LikeU12, I>) -> Self {
-        Self::DU12(value)
+fn is_selected(cli: &Cli, path: &Path) -> bool{
+    let compressed = path.extension().is_some_and(|extension| extension == "zwl" || extension == "Z");
+    match cli.mode{
+        Mode::Encode => !compressed,
+        Mode::Decode | Mode::Test => compressed,
+        _ => true,
     }
 }
-impl<I: Read> From::<ZwlBitDecoder<LikeU16, I>> for ZwlDecoderE<I>{
-    fn from(value: ZwlBitDecoder<LikeU16, I>) -> Self {
-        Self::DU16(value)
+
+fn decode_options(cli: &Cli, preset: Option<&PresetDictionary>) -> DecodeOptions{
+    let mut options = DecodeOptions { preset: preset.cloned(), ..Default::default() };
+    if let Some(threads) = cli.threads{
+        options.threads = threads;
     }
+    options
 }
 
-impl<I: Read> From::<ZwlBitDecoder<LikeU32, I>> for ZwlDecoderE<I>{
-    fn from(value: ZwlBitDecoder<LikeU32, I>) -> Self {
-        Self::DU32(value)
+// Packs the inputs into one archive named after the first one, directories with everything in them
+fn pack(cli: &Cli, inputs: &[PathBuf], preset: Option<&PresetDictionary>) -> io::Result<()>{
+    let output_path = match output_target(cli, &inputs[0])?{
+        Target::File(path) => Some(path),
+        Target::Stdout => None,
+        Target::Skip => return Ok(()),
+    };
+    let mut archive = ZwlArchiveWriter::new(create_output(output_path.as_deref())?)?;
+    archive.bit_size = cli.encoding.unwrap_or(12);
+    archive.filled = cli.filled.clone().into();
+    archive.preset = preset.cloned();
+    archive.block_size = cli.block_size;
+    archive.seekable = cli.seekable;
+    if let Some(threads) = cli.threads{
+        archive.threads = threads;
     }
+    // The archive is skipped when it is inside a packed directory
+    let archive_path = output_path.as_deref().map(fs::canonicalize).transpose()?;
+    for input in inputs{
+        let Some(name) = input.file_name().map(PathBuf::from).or_else(|| fs::canonicalize(input).ok()?.file_name().map(PathBuf::from)) else {
+            return Err(io::Error::other(format!("{} has no name to store in the archive", input.display())));
+        };
+        pack_path(&mut archive, input, name.to_string_lossy().into_owned(), archive_path.as_deref())?;
+    }
+    archive.finish()?.flush()
 }
-impl<I: Read> From::<ZwlBitDecoder<LikeU64, I>> for ZwlDecoderE<I>{
-    fn from(value: ZwlBitDecoder<LikeU64, I>) -> Self {
-        Self::DU64(value)
+
+fn pack_path<W: Write>(archive: &mut ZwlArchiveWriter<W>, path: &Path, name: String, archive_path: Option<&Path>) -> io::Result<()>{
+    let metadata = fs::symlink_metadata(path)?;
+    let mtime = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map_or(0, |time| time.as_secs());
+    let entry = ArchiveEntry::new(name.clone(), EntryKind::File, mtime, file_mode(&metadata));
+    if metadata.is_dir(){
+        archive.add_directory(entry)?;
+        let mut children = fs::read_dir(path)?.map(|child| child.map(|child| child.path())).collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+        for child in children{
+            let child_name = child.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            pack_path(archive, &child, format!("{name}/{child_name}"), archive_path)?;
+        }
     }
+    else if metadata.is_file(){
+        if archive_path.is_some_and(|archive_path| fs::canonicalize(path).is_ok_and(|path| path == archive_path)){
+            return Ok(());
+        }
+        archive.add_file(entry, File::open(path)?)?;
+    }
+    else{
+        eprintln!("{}: Not a regular file or directory, skipped", path.display());
+    }
+    Ok(())
 }
 
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32{
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
 
-pub fn get_decoder<I: Read>(mut file: I) -> std::io::Result<ZwlDecoderE<I>> {
-    let mut buffer = [0, 0];
-    file.read_exact(&mut buffer)?;
-    let index_bit_size = buffer[0];
-    let filled_behaviour = buffer[1];
-    let filled_behaviour = match filled_behaviour{
-        0 => FilledBehaviour::Clear,
-        1 => FilledBehaviour::Freeze,
-        _ => return Err(std::io::Error::other("Header does not say if dictionary should clear or freeze when it is full"))
-    };
-    match index_bit_size{
-        12 => {
-            Ok(ZwlDecoderE::from(ZwlBitDecoder::<LikeU12, I>::new(file, filled_behaviour)))
-        }
-        16 => {
-            Ok(ZwlDecoderE::from(ZwlBitDecoder::<LikeU16, I>::new(file, filled_behaviour)))
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32{
+    let mode = if metadata.is_dir(){ 0o755 } else { 0o644 };
+    if metadata.permissions().readonly(){ mode & 0o555 } else { mode }
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> io::Result<()>{
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(path: &Path, mode: u32) -> io::Result<()>{
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(unix)]
+fn set_dir_modified(path: &Path, time: SystemTime) -> io::Result<()>{
+    File::open(path)?.set_modified(time)
+}
+
+// Directories cannot be opened as files here, so they keep the time of unpacking
+#[cfg(not(unix))]
+fn set_dir_modified(_path: &Path, _time: SystemTime) -> io::Result<()>{
+    Ok(())
+}
+
+fn open_archive(path: &Path, preset: Option<&PresetDictionary>) -> io::Result<ZwlArchiveReader<io::BufReader<File>>>{
+    if is_stdio(path){
+        return Err(io::Error::other("Archives are read with random access, standard input cannot be used"));
+    }
+    let options = DecodeOptions { preset: preset.cloned(), ..Default::default() };
+    Ok(ZwlArchiveReader::with_options(io::BufReader::new(File::open(path)?), &options)?)
+}
+
+// Unpacks into the directory given by --output, or one named after the archive
+fn unpack(cli: &Cli, input_path: &Path, preset: Option<&PresetDictionary>) -> io::Result<()>{
+    let mut archive = open_archive(input_path, preset)?;
+    let destination = cli.output.clone().unwrap_or_else(|| input_path.with_extension(""));
+    if destination == input_path{
+        return Err(io::Error::other("Output directory would replace the archive"));
+    }
+    // The default name can be a file that was packed next to the archive
+    if destination.exists() && !destination.is_dir(){
+        return Err(io::Error::other(format!("Output directory {} is an existing file, pass --output to unpack elsewhere", destination.display())));
+    }
+    fs::create_dir_all(&destination)?;
+    for index in 0..archive.entries.len(){
+        let entry = archive.entries[index].clone();
+        let path = destination.join(&entry.path);
+        if entry.kind == EntryKind::Directory{
+            fs::create_dir_all(&path)?;
+            continue;
         }
-        32 => {
-            Ok(ZwlDecoderE::from(ZwlBitDecoder::<LikeU32, I>::new(file, filled_behaviour)))
+        if let Some(parent) = path.parent(){
+            fs::create_dir_all(parent)?;
         }
-        64 => {
-            Ok(ZwlDecoderE::from(ZwlBitDecoder::<LikeU64, I>::new(file, filled_behaviour)))
+        if path.exists() && !cli.overwrite
+            && !confirm(cli, format!("File {:?} already exists. Do you want to replace it?", path))?{
+            println!("Skipped {:?}", path);
+            continue;
         }
-        _ =>{
-            Err(std::io::Error::other("Only LikeU12, u16, u32 and u64 indexes were implemented"))
+        let mut output = File::create(&path)?;
+        archive.read_entry(index, &mut output)?;
+        output.set_modified(UNIX_EPOCH + Duration::from_secs(entry.mtime))?;
+        set_file_mode(&path, entry.mode)?;
+    }
+    // Directories get their times and permissions last, writing the files would change them otherwise.
+    // Children come first so that a read-only parent does not get in the way
+    for entry in archive.entries.iter().rev().filter(|entry| entry.kind == EntryKind::Directory){
+        let path = destination.join(&entry.path);
+        set_dir_modified(&path, UNIX_EPOCH + Duration::from_secs(entry.mtime))?;
+        set_file_mode(&path, entry.mode)?;
+    }
+    Ok(())
+}
+
+fn list(input_path: &Path, preset: Option<&PresetDictionary>) -> io::Result<()>{
+    let archive = open_archive(input_path, preset)?;
+    let mut output = io::BufWriter::new(io::stdout().lock());
+    let (mut size, mut compressed_len) = (0, 0);
+    for entry in &archive.entries{
+        let (kind, suffix) = match entry.kind{
+            EntryKind::File => ('-', ""),
+            EntryKind::Directory => ('d', "/"),
+        };
+        writeln!(output, "{kind}{} {:>12} {:>12} {} {}{suffix}", mode_string(entry.mode), entry.size, entry.compressed_len, date_string(entry.mtime), entry.path)?;
+        size += entry.size;
+        compressed_len += entry.compressed_len;
+    }
+    writeln!(output, "{} entries, {size} bytes, {compressed_len} compressed", archive.entries.len())?;
+    output.flush()
+}
+
+fn mode_string(mode: u32) -> String{
+    (0..9).rev().map(|bit| if mode & (1 << bit) == 0 { '-' } else { ['x', 'w', 'r'][bit % 3] }).collect()
+}
+
+// UTC date of a Unix time, using the days-to-civil conversion of the proleptic Gregorian calendar
+fn date_string(mtime: u64) -> String{
+    let days = (mtime / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}", mtime % 86_400 / 3600, mtime % 3600 / 60)
+}
+
+fn read_preset(path: &Path) -> io::Result<PresetDictionary>{
+    Ok(PresetDictionary::read_from(&mut io::BufReader::new(File::open(path)?))?)
+}
+
+// Reads the files one after another, each is opened once the previous one ends
+struct Concat{
+    files: std::vec::IntoIter<PathBuf>,
+    current: Box<dyn Read>,
+}
+
+impl Read for Concat{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop{
+            let count = self.current.read(buf)?;
+            if count > 0 || buf.is_empty(){
+                return Ok(count);
+            }
+            match self.files.next(){
+                Some(path) => self.current = open_input(&path)?,
+                None => return Ok(0),
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+// Appends to the whole file name, so names without an extension do not get a second dot
+fn append_extension(path: &Path, extension: &str) -> PathBuf{
+    let path = match path.file_name(){
+        Some(_) => path.to_path_buf(),
+        None => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+    };
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+fn is_stdio(path: &Path) -> bool{
+    path.as_os_str() == STDIO
+}
+
+fn open_input(path: &Path) -> io::Result<Box<dyn Read>>{
+    if is_stdio(path){
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    Ok(Box::new(File::open(path)?))
+}
+
+// No path means standard output
+fn create_output(path: Option<&Path>) -> io::Result<Box<dyn Write>>{
+    match path{
+        Some(path) => Ok(Box::new(File::create(path)?)),
+        None => Ok(Box::new(io::BufWriter::new(io::stdout().lock()))),
+    }
+}
+
+// Prompts only when there is a terminal to answer on, `--yes` accepts every prompt
+fn confirm(cli: &Cli, prompt: String) -> io::Result<bool>{
+    if cli.yes{
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal(){
+        return Err(io::Error::other(format!("{prompt} There is no terminal to answer on, pass --yes to accept")));
+    }
+    Confirm::new().with_prompt(prompt).interact().map_err(io::Error::other)
+}
+
+// Standard input cannot be opened twice, so the magic is read and put back in front of the input
+fn peek_unix_compress<R: Read>(mut input: R) -> io::Result<(bool, impl Read)>{
+    let mut magic = vec![];
+    (&mut input).take(Z_MAGIC.len() as u64).read_to_end(&mut magic)?;
+    Ok((magic == Z_MAGIC, io::Cursor::new(magic).chain(input)))
+}
+
+fn is_unix_compress(path: &Path) -> io::Result<bool>{
+    let mut magic = [0; 2];
+    Ok(File::open(path)?.read_exact(&mut magic).is_ok() && magic == Z_MAGIC)
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf{
+        let dir = std::env::temp_dir().join(format!("zwl-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn existing_second_path_is_an_input(){
+        let dir = scratch_dir("two-inputs");
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        fs::write(&a, b"first file, first file").unwrap();
+        fs::write(&b, b"second file, second file").unwrap();
+        let mut cli = Cli::parse_from([Path::new("zwl"), &a, &b]);
+        cli.take_positional_output();
+        assert!(cli.output.is_none());
+        assert!(!compress_files(&cli, None));
+        assert_eq!(fs::read(&a).unwrap(), b"first file, first file");
+        assert_eq!(fs::read(&b).unwrap(), b"second file, second file");
+        assert!(dir.join("a.txt.zwl").exists());
+        assert!(dir.join("b.txt.zwl").exists());
+
+        // Neither --yes nor --delete take an output from the positional paths
+        let mut cli = Cli::parse_from([Path::new("zwl"), Path::new("-y"), Path::new("--delete"), &a, &dir.join("new.zwl")]);
+        cli.take_positional_output();
+        assert!(cli.output.is_none());
+        // A new file or an existing .zwl file still is the output
+        let mut cli = Cli::parse_from([Path::new("zwl"), &a, &dir.join("new")]);
+        cli.take_positional_output();
+        assert_eq!(cli.output, Some(dir.join("new")));
+        let mut cli = Cli::parse_from([Path::new("zwl"), &a, &dir.join("b.txt.zwl")]);
+        cli.take_positional_output();
+        assert_eq!(cli.output, Some(dir.join("b.txt.zwl")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_several_inputs_into_named_archive(){
+        let dir = scratch_dir("pack");
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        fs::write(&a, b"first file, first file").unwrap();
+        fs::write(&b, b"second file, second file").unwrap();
+        let archive = dir.join("x.zwla");
+        let cli = Cli::parse_from([Path::new("zwl"), Path::new("-m"), Path::new("pack"), &a, &b, Path::new("--output"), &archive]);
+        pack(&cli, &cli.input_files, None).unwrap();
+        let cli = Cli::parse_from([Path::new("zwl"), Path::new("-m"), Path::new("unpack"), &archive]);
+        unpack(&cli, &archive, None).unwrap();
+        assert_eq!(fs::read(dir.join("x/a.txt")).unwrap(), b"first file, first file");
+        assert_eq!(fs::read(dir.join("x/b.txt")).unwrap(), b"second file, second file");
+
+        // Named after the first input, the archive would unpack into it
+        let cli = Cli::parse_from([Path::new("zwl"), Path::new("-m"), Path::new("pack"), &a, &b]);
+        pack(&cli, &cli.input_files, None).unwrap();
+        let archive = dir.join("a.txt.zwla");
+        let cli = Cli::parse_from([Path::new("zwl"), Path::new("-m"), Path::new("unpack"), &archive]);
+        assert!(unpack(&cli, &archive, None).unwrap_err().to_string().contains("--output"));
+        assert_eq!(fs::read(&a).unwrap(), b"first file, first file");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+// Times the Vec<bool> bit I/O used before against the u64 accumulators of zwl_gs::bit_io,
+// the unit tests check that both write the same layout. Run with `cargo bench --bench bit_io`.
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use zwl_gs::{bit_encoder::ZwlBitEncoder, bit_io, code_width::LikeU16, decode_auto, dictionary::FilledBehaviour, traits::{RequiredBits, ToBits}};
+
+const CODES: usize = 1 << 20;
+const ROUNDS: u32 = 5;
+
+fn codes() -> Vec<(LikeU16, usize)>{
+    let mut state: u32 = 0x2545_F491;
+    (0..CODES).map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let width = 9 + (state >> 28) as usize % 8;
+        let code = LikeU16::try_from((state >> 8) as usize & ((1 << width) - 1)).unwrap();
+        (code, width.max(code.required_bits()))
+    }).collect()
+}
+
+fn measure<F: FnMut()>(name: &str, bytes: usize, mut run: F){
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS{
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed());
+    }
+    println!("{name:<28} {:>10.2?} {:>10.1} MiB/s", best, bytes as f64 / best.as_secs_f64() / (1024.0 * 1024.0));
+}
+
+fn write_bool_vecs(codes: &[(LikeU16, usize)]) -> Vec<u8>{
+    let mut output = vec![];
+    {
+        let mut writer = bit_writer_reader::bit_writter::BitWriter::new(&mut output);
+        for (code, width) in codes{
+            let mut bits = code.bits_vec();
+            bits.resize(*width, false);
+            writer.write_bits(&bits).unwrap();
+        }
+    }
+    output
+}
+
+fn write_words(codes: &[(LikeU16, usize)]) -> Vec<u8>{
+    let mut output = vec![];
+    {
+        let mut writer = bit_io::BitWriter::new(&mut output);
+        for (code, width) in codes{
+            writer.write_bits(code.0, *width as u32).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    output
+}
+
+fn main(){
+    let codes = codes();
+    let packed = write_words(&codes);
+
+    println!("{} codes, {} packed bytes", codes.len(), packed.len());
+    measure("write Vec<bool>", packed.len(), || { black_box(write_bool_vecs(black_box(&codes))); });
+    measure("write u64 accumulator", packed.len(), || { black_box(write_words(black_box(&codes))); });
+    measure("read Vec<bool>", packed.len(), || {
+        let mut reader = bit_writer_reader::bit_reader::BitReader::new(&packed[..]);
+        for (_, width) in &codes{
+            let bits = reader.read_bits(*width).unwrap();
+            black_box(LikeU16::try_from(bits.as_slice()).unwrap());
+        }
+    });
+    measure("read u64 accumulator", packed.len(), || {
+        let mut reader = bit_io::BitReader::new(&packed[..]);
+        for (_, width) in &codes{
+            black_box(reader.read_bits(*width as u32).unwrap());
+        }
+    });
+
+    let text: Vec<u8> = codes.iter().map(|(code, _)| b"abcdefghij klmnop\n"[code.0 as usize % 18]).collect();
+    let mut encoded = vec![];
+    ZwlBitEncoder::<LikeU16, _>::new(&text[..], FilledBehaviour::Clear).encode(&mut encoded).unwrap();
+    measure("encode LikeU16", text.len(), || {
+        let mut output = vec![];
+        ZwlBitEncoder::<LikeU16, _>::new(&text[..], FilledBehaviour::Clear).encode(&mut output).unwrap();
+        black_box(output);
+    });
+    measure("decode LikeU16", text.len(), || {
+        let mut output = Vec::with_capacity(text.len());
+        decode_auto(&encoded[..], &mut output).unwrap();
+        black_box(output);
+    });
+}
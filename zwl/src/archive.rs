@@ -0,0 +1,221 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{auto_decoder::{get_decoder_with_options, DecodeOptions}, bit_encoder::ZwlBitEncoder, block::{default_threads, read_payload}, dictionary::FilledBehaviour, error::ZwlError, preset::PresetDictionary, stats::Counter, with_code_width};
+
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"ZWLA";
+pub const ARCHIVE_VERSION: u8 = 1;
+pub const DIRECTORY_MAGIC: [u8; 4] = *b"ZWLC";
+// Offset of the central directory and its magic at the very end of the archive
+const ARCHIVE_TRAILER_LEN: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryKind{
+    File,
+    Directory,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveEntry{
+    // Relative path with `/` between components
+    pub path: String,
+    pub kind: EntryKind,
+    // Length of the original data
+    pub size: u64,
+    // Seconds since the Unix epoch
+    pub mtime: u64,
+    // Unix permission bits
+    pub mode: u32,
+    // Position and length of the entry's .zwl stream in the archive
+    pub offset: u64,
+    pub compressed_len: u64,
+}
+
+impl ArchiveEntry{
+    pub fn new(path: impl Into<String>, kind: EntryKind, mtime: u64, mode: u32) -> Self{
+        Self { path: path.into(), kind, size: 0, mtime, mode, offset: 0, compressed_len: 0 }
+    }
+}
+
+// Entries are unpacked below a directory, so they cannot be absolute or climb out of it
+fn check_path(path: &str) -> Result<(), ZwlError>{
+    if path.is_empty() || path.contains('\\') || path.split('/').any(|component| matches!(component, "" | "." | "..")){
+        return Err(ZwlError::BadHeader(format!("Unsafe archive entry path {path:?}")));
+    }
+    Ok(())
+}
+
+// Writes every file as its own .zwl stream, followed by a central directory of all entries
+pub struct ZwlArchiveWriter<W: Write>{
+    output: Counter<W>,
+    pub bit_size: u8,
+    pub filled: FilledBehaviour,
+    // Primes every entry, readers need the same preset
+    pub preset: Option<PresetDictionary>,
+    // Block mode settings of the entry streams, as on `ZwlBitEncoder`
+    pub block_size: Option<u32>,
+    pub threads: usize,
+    pub seekable: bool,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl<W: Write> ZwlArchiveWriter<W>{
+    pub fn new(output: W) -> Result<Self, ZwlError>{
+        let mut output = Counter::new(output);
+        output.write_all(&ARCHIVE_MAGIC)?;
+        output.write_all(&[ARCHIVE_VERSION])?;
+        Ok(Self{
+            output,
+            bit_size: 12,
+            filled: FilledBehaviour::Clear,
+            preset: None,
+            block_size: None,
+            threads: default_threads(),
+            seekable: false,
+            entries: vec![],
+        })
+    }
+    pub fn add_file<R: Read>(&mut self, mut entry: ArchiveEntry, input: R) -> Result<(), ZwlError>{
+        check_path(&entry.path)?;
+        let mut input = Counter::new(input);
+        let offset = self.output.count();
+        with_code_width!(self.bit_size, W => {
+            let mut encoder = ZwlBitEncoder::<W, _>::new(&mut input, self.filled);
+            if let Some(preset) = &self.preset{
+                encoder.prime(preset);
+            }
+            encoder.block_size = self.block_size;
+            encoder.threads = self.threads;
+            encoder.seekable = self.seekable;
+            encoder.encode(&mut self.output)?
+        },
+            _ => return Err(ZwlError::UnsupportedWidth(self.bit_size)));
+        entry.kind = EntryKind::File;
+        entry.size = input.count();
+        entry.offset = offset;
+        entry.compressed_len = self.output.count() - offset;
+        self.entries.push(entry);
+        Ok(())
+    }
+    pub fn add_directory(&mut self, mut entry: ArchiveEntry) -> Result<(), ZwlError>{
+        check_path(&entry.path)?;
+        entry.kind = EntryKind::Directory;
+        entry.size = 0;
+        entry.offset = self.output.count();
+        entry.compressed_len = 0;
+        self.entries.push(entry);
+        Ok(())
+    }
+    // Writes the central directory and returns the output
+    pub fn finish(mut self) -> Result<W, ZwlError>{
+        let directory_offset = self.output.count();
+        let count = u32::try_from(self.entries.len()).map_err(|_| std::io::Error::other("Too many archive entries"))?;
+        let output = &mut self.output;
+        output.write_all(&count.to_le_bytes())?;
+        for entry in &self.entries{
+            let path_len = u16::try_from(entry.path.len()).map_err(|_| std::io::Error::other("Archive entry path is too long"))?;
+            output.write_all(&[match entry.kind{
+                EntryKind::File => 0,
+                EntryKind::Directory => 1,
+            }])?;
+            output.write_all(&entry.mode.to_le_bytes())?;
+            output.write_all(&entry.mtime.to_le_bytes())?;
+            output.write_all(&entry.size.to_le_bytes())?;
+            output.write_all(&entry.offset.to_le_bytes())?;
+            output.write_all(&entry.compressed_len.to_le_bytes())?;
+            output.write_all(&path_len.to_le_bytes())?;
+            output.write_all(entry.path.as_bytes())?;
+        }
+        output.write_all(&directory_offset.to_le_bytes())?;
+        output.write_all(&DIRECTORY_MAGIC)?;
+        output.flush()?;
+        Ok(self.output.into_inner())
+    }
+}
+
+fn read_u64<I: Read>(input: &mut I) -> Result<u64, ZwlError>{
+    let mut value = [0; 8];
+    input.read_exact(&mut value)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+// Reads the central directory up front, so any entry can be decoded without the ones before it
+pub struct ZwlArchiveReader<R: Read + Seek>{
+    input: R,
+    options: DecodeOptions,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl<R: Read + Seek> ZwlArchiveReader<R>{
+    pub fn new(input: R) -> Result<Self, ZwlError>{
+        Self::with_options(input, &DecodeOptions::default())
+    }
+    pub fn with_options(mut input: R, options: &DecodeOptions) -> Result<Self, ZwlError>{
+        let mut magic = [0; 5];
+        input.read_exact(&mut magic)?;
+        if magic[..4] != ARCHIVE_MAGIC{
+            return Err(ZwlError::BadHeader("Magic number of archive does not match".into()));
+        }
+        if magic[4] != ARCHIVE_VERSION{
+            return Err(ZwlError::BadHeader(format!("Unsupported archive version {}", magic[4])));
+        }
+        input.seek(SeekFrom::End(-(ARCHIVE_TRAILER_LEN as i64)))?;
+        let directory_offset = read_u64(&mut input)?;
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if magic != DIRECTORY_MAGIC{
+            return Err(ZwlError::BadHeader("Magic number of archive directory does not match".into()));
+        }
+        input.seek(SeekFrom::Start(directory_offset))?;
+        let mut count = [0; 4];
+        input.read_exact(&mut count)?;
+        let mut entries = vec![];
+        for _ in 0..u32::from_le_bytes(count){
+            let mut fields = [0; 5];
+            input.read_exact(&mut fields)?;
+            let kind = match fields[0]{
+                0 => EntryKind::File,
+                1 => EntryKind::Directory,
+                kind => return Err(ZwlError::BadHeader(format!("Unknown archive entry kind {kind}"))),
+            };
+            let mode = u32::from_le_bytes(fields[1..].try_into().unwrap());
+            let mtime = read_u64(&mut input)?;
+            let size = read_u64(&mut input)?;
+            let offset = read_u64(&mut input)?;
+            let compressed_len = read_u64(&mut input)?;
+            let mut path_len = [0; 2];
+            input.read_exact(&mut path_len)?;
+            let path = String::from_utf8(read_payload(&mut input, u16::from_le_bytes(path_len).into())?)
+                .map_err(|_| ZwlError::BadHeader("Archive entry path is not UTF-8".into()))?;
+            check_path(&path)?;
+            if offset.checked_add(compressed_len).is_none_or(|end| end > directory_offset){
+                return Err(ZwlError::BadHeader(format!("Archive entry {path:?} reaches past its data")));
+            }
+            entries.push(ArchiveEntry { path, kind, size, mtime, mode, offset, compressed_len });
+        }
+        Ok(Self{
+            input,
+            options: options.clone(),
+            entries,
+        })
+    }
+    pub fn find(&self, path: &str) -> Option<usize>{
+        self.entries.iter().position(|entry| entry.path == path)
+    }
+    // Decodes one entry, directories write nothing
+    pub fn read_entry<O: Write>(&mut self, index: usize, output: O) -> Result<(), ZwlError>{
+        let entry = self.entries.get(index)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("No archive entry {index}, the archive has {}", self.entries.len())))?;
+        if entry.kind == EntryKind::Directory{
+            return Ok(());
+        }
+        self.input.seek(SeekFrom::Start(entry.offset))?;
+        let input = (&mut self.input).take(entry.compressed_len);
+        let mut output = Counter::new(output);
+        let (_, mut decoder) = get_decoder_with_options(input, &self.options)?;
+        decoder.decode(&mut output)?;
+        if output.count() != entry.size{
+            return Err(std::io::Error::other(format!("Archive entry {:?} decoded to {} bytes instead of {}", entry.path, output.count(), entry.size)).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::{io::{Read, Write}, ops::Sub};
+
+use crate::{bit_decoder::ZwlBitDecoder, block::{default_threads, ZwlBlockDecoder}, members::ZwlMemberDecoder, error::ZwlError, stats::CodingStats, header::ZwlHeader, code_width::CodeWidth, preset::PresetDictionary, traits::{CustomWriteSize, TrailingOnesR}, with_code_width};
+
+macro_rules! decoders{
+    ($($variant:ident $bits:literal),*) => {
+        pub enum ZwlDecoderE<I: Read>{
+            $($variant(ZwlBitDecoder<CodeWidth<$bits>, I>),)*
+            Blocks(ZwlBlockDecoder<I>),
+        }
+
+        $(impl<I: Read> From::<ZwlBitDecoder<CodeWidth<$bits>, I>> for ZwlDecoderE<I>{
+            fn from(value: ZwlBitDecoder<CodeWidth<$bits>, I>) -> Self {
+                Self::$variant(value)
+            }
+        })*
+
+        impl<I: Read> ZwlDecoderE<I>{
+            pub fn decode<O: Write>(&mut self, output: O) -> Result<(), ZwlError>{
+                match self{
+                    $(ZwlDecoderE::$variant(zwl_decoder) => zwl_decoder.decode(output),)*
+                    ZwlDecoderE::Blocks(block_decoder) => block_decoder.decode(output),
+                }
+            }
+            pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError>{
+                match self{
+                    $(ZwlDecoderE::$variant(zwl_decoder) => zwl_decoder.next_sequence(),)*
+                    ZwlDecoderE::Blocks(block_decoder) => block_decoder.next_sequence(),
+                }
+            }
+            pub fn stats(&self) -> CodingStats{
+                match self{
+                    $(ZwlDecoderE::$variant(zwl_decoder) => zwl_decoder.stats(),)*
+                    ZwlDecoderE::Blocks(block_decoder) => block_decoder.stats(),
+                }
+            }
+            pub fn into_input(self) -> (Vec<u8>, I){
+                match self{
+                    $(ZwlDecoderE::$variant(zwl_decoder) => zwl_decoder.into_input(),)*
+                    ZwlDecoderE::Blocks(block_decoder) => (vec![], block_decoder.into_input()),
+                }
+            }
+        }
+    };
+}
+
+decoders!(
+    W9 9, W10 10, W11 11, W12 12, W13 13, W14 14, W15 15,
+    W16 16, W17 17, W18 18, W19 19, W20 20, W21 21, W22 22,
+    W23 23, W24 24, W25 25, W26 26, W27 27, W28 28, W29 29,
+    W30 30, W31 31, W32 32, W33 33, W34 34, W35 35, W36 36,
+    W37 37, W38 38, W39 39, W40 40, W41 41, W42 42, W43 43,
+    W44 44, W45 45, W46 46, W47 47, W48 48, W49 49, W50 50,
+    W51 51, W52 52, W53 53, W54 54, W55 55, W56 56, W57 57,
+    W58 58, W59 59, W60 60, W61 61, W62 62, W63 63, W64 64
+);
+
+
+#[derive(Clone, Debug)]
+pub struct DecodeOptions{
+    pub verify_checksum: bool,
+    // Only used for streams whose header asks for a preset
+    pub preset: Option<PresetDictionary>,
+    // Blocks decoded at once for streams written in block mode
+    pub threads: usize,
+}
+
+impl Default for DecodeOptions{
+    fn default() -> Self {
+        Self { verify_checksum: true, preset: None, threads: default_threads() }
+    }
+}
+
+pub fn get_decoder<I: Read>(file: I) -> Result<ZwlDecoderE<I>, ZwlError> {
+    Ok(get_decoder_and_header(file)?.1)
+}
+
+pub fn get_decoder_and_header<I: Read>(file: I) -> Result<(ZwlHeader, ZwlDecoderE<I>), ZwlError> {
+    get_decoder_with_options(file, &DecodeOptions::default())
+}
+
+pub fn get_decoder_with_options<I: Read>(mut file: I, options: &DecodeOptions) -> Result<(ZwlHeader, ZwlDecoderE<I>), ZwlError> {
+    let header = ZwlHeader::read_from(&mut file)?;
+    check_preset(&header, options)?;
+    if header.block_size.is_some(){
+        let decoder = ZwlBlockDecoder::new(file, header.clone(), options.clone());
+        return Ok((header, ZwlDecoderE::Blocks(decoder)));
+    }
+    let decoder = with_code_width!(header.bit_size, W => ZwlDecoderE::from(new_decoder::<W, I>(file, &header, options)),
+        _ => return Err(ZwlError::UnsupportedWidth(header.bit_size)));
+    Ok((header, decoder))
+}
+
+pub(crate) fn check_preset(header: &ZwlHeader, options: &DecodeOptions) -> Result<(), ZwlError>{
+    if let Some(expected) = header.preset_id{
+        let actual = options.preset.as_ref().map(PresetDictionary::id);
+        if actual != Some(expected){
+            return Err(ZwlError::PresetMismatch { expected, actual });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn new_decoder<T, I>(file: I, header: &ZwlHeader, options: &DecodeOptions) -> ZwlBitDecoder<T, I>
+where T: TryInto<usize, Error: std::fmt::Debug> + std::fmt::Debug + Default + From<u8> + PartialOrd + Copy + Sub<Output = T>
+        + TryFrom<usize, Error: std::fmt::Debug> + min_max_traits::Max + CustomWriteSize + TrailingOnesR,
+    I: Read{
+    let mut decoder = ZwlBitDecoder::from_header(file, header, options.verify_checksum);
+    if header.preset_id.is_some() && let Some(preset) = &options.preset{
+        decoder.prime(preset);
+    }
+    decoder
+}
+
+// Decodes every member of concatenated streams
+pub fn decode_auto<I: Read, O: Write>(input: I, output: O) -> Result<(), ZwlError>{
+    ZwlMemberDecoder::new(input, &DecodeOptions::default())?.decode(output)
+}
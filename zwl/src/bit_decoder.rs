@@ -1,11 +1,9 @@
-use std::{fmt::Debug, io::{Read, Write}, ops::Sub};
+use std::{io::{Read, Write}, ops::Sub};
 
-use bit_writer_reader::bit_reader::BitReader;
-
-use crate::{dictionary::{Dictionary, FilledBehaviour}, traits::{CustomWriteSize, TrailingOnesR, RequiredBits}};
+use crate::{bit_encoder::implicit_width, bit_io::BitReader, checksum::{Crc32, Trailer, TrailerReader, CHECKSUM_LEN}, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError, header::ZwlHeader, preset::PresetDictionary, stats::CodingStats, traits::{CustomWriteSize, TrailingOnesR, RequiredBits}};
 
 pub struct ZwlBitDecoder<T: TryInto<usize>, I: Read>{
-    input: I,
+    input: BitReader<TrailerReader<I>>,
     pub dictionary: Dictionary<T>,
     // sequence: Vec<u8>,
     //current_symbol: Option<u8>,
@@ -13,6 +11,13 @@ pub struct ZwlBitDecoder<T: TryInto<usize>, I: Read>{
     old_sequence: Vec<u8>,
     // old_symbol: Option<u8>,
     old_index: Option<T>,
+    size_req: usize,
+    trailer: Option<Trailer>,
+    checksum_in_stream: bool,
+    checksum: Option<Crc32>,
+    read_bits_count: u64,
+    finished: bool,
+    stats: CodingStats,
 }
 
 impl<T, I> ZwlBitDecoder<T, I>
@@ -20,10 +25,54 @@ where
     T: TryInto<usize, Error: std::fmt::Debug> + std::fmt::Debug,
     I: Read{
     pub fn new(input: I, dictionary_filled: FilledBehaviour) -> Self{
+        let mut decoder = Self::new_legacy(input, dictionary_filled);
+        decoder.dictionary.reserved = 2;
+        decoder.dictionary.implicit_width = true;
+        decoder
+    }
+    pub fn new_legacy(input: I, dictionary_filled: FilledBehaviour) -> Self{
+        Self::with_trailer(TrailerReader::new(input, 0), dictionary_filled)
+    }
+    pub fn from_header(input: I, header: &ZwlHeader, verify_checksum: bool) -> Self{
+        let hold = if header.checksum && !header.end_code { CHECKSUM_LEN } else { 0 };
+        let input = TrailerReader::new(input, hold);
+        let trailer = input.trailer();
+        let mut decoder = Self::with_trailer(input, header.filled);
+        decoder.dictionary.implicit_width = header.implicit_width;
+        if header.clear_code{
+            decoder.dictionary.reserved = 2;
+        }
+        else if header.end_code{
+            decoder.dictionary.reserved = 1;
+        }
+        if header.checksum{
+            if header.end_code{
+                decoder.checksum_in_stream = true;
+            }
+            else{
+                decoder.trailer = Some(trailer);
+            }
+            if verify_checksum{
+                decoder.checksum = Some(Crc32::new());
+            }
+        }
+        decoder
+    }
+    // Input bytes count the codes without the header and the trailer
+    pub fn stats(&self) -> CodingStats{
+        CodingStats { input_bytes: self.read_bits_count.div_ceil(8), dictionary_len: self.dictionary.len(), ..self.stats }
+    }
+    // Bytes read past the end code and the rest of the input, where the next member starts
+    pub fn into_input(self) -> (Vec<u8>, I){
+        let (rest, input) = self.input.into_remainder();
+        (rest, input.into_inner())
+    }
+    fn with_trailer(input: TrailerReader<I>, dictionary_filled: FilledBehaviour) -> Self{
         let mut dictionary = Dictionary::default();
         dictionary.filled = dictionary_filled;
         Self{
-            input,
+            input: BitReader::new(input),
+            size_req: dictionary.required_bits(),
             dictionary,
             // sequence: vec![],
             // current_symbol: None,
@@ -31,64 +80,148 @@ where
             old_sequence: vec![],
             old_index: None,
             // old_symbol: None,
+            trailer: None,
+            checksum_in_stream: false,
+            checksum: None,
+            read_bits_count: 0,
+            finished: false,
+            stats: CodingStats::default(),
         }
     }
 }
 
 impl<T, I> ZwlBitDecoder<T, I>
 where 
-    T: TryInto<usize, Error: std::fmt::Debug> + std::fmt::Debug + for<'a> TryFrom<&'a [bool], Error: Debug> + Default + From<u8> + PartialOrd + Copy + Sub<Output = T> 
+    T: TryInto<usize, Error: std::fmt::Debug> + std::fmt::Debug + Default + From<u8> + PartialOrd + Copy + Sub<Output = T> 
         + TryFrom<usize, Error: std::fmt::Debug> + min_max_traits::Max + CustomWriteSize 
         //+ LeadingZerosR 
         + TrailingOnesR,
     I: Read{
-    pub fn decode<O: Write>(&mut self, mut output: O) -> std::io::Result<()> {
-        let mut readable = BitReader::new(&mut self.input);
-        //let size_req = T::custom_size();
-        let mut size_req = self.dictionary.required_bits();
-
-        let binding = readable.read_bits(size_req)?;
-        let index_v: &[bool] = binding.as_slice();
-        let index = T::try_from(index_v).unwrap();
-        // println!("TRAILING ONES: {}, index: {index:?}, size_req: {size_req}", index.trailing_ones());
-        let sequence = vec![self.dictionary[index].0];
-
-        output.write(&sequence)?;
-        self.old_index = Some(index);
-        self.old_sequence = sequence;
-        let mut result = readable.read_bits(size_req);
-        while let Ok(index_v) = result{
-            let index = T::try_from(index_v.as_slice()).unwrap();
-            if index.trailing_ones() == size_req{
-                if T::custom_size() == size_req{
-                    size_req = 9;
+    pub fn prime(&mut self, preset: &PresetDictionary){
+        self.dictionary.prime(preset);
+        self.size_req = self.dictionary.required_bits();
+    }
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        while let Some(sequence) = self.next_sequence()?{
+            output.write_all(sequence)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        if !self.read_sequence()?{
+            self.verify_trailer()?;
+            return Ok(None);
+        }
+        if let Some(checksum) = self.checksum.as_mut(){
+            checksum.update(&self.old_sequence);
+        }
+        self.stats.output_bytes += self.old_sequence.len() as u64;
+        Ok(Some(&self.old_sequence))
+    }
+    fn verify_trailer(&mut self) -> Result<(), ZwlError> {
+        let expected = if self.checksum_in_stream{
+            self.checksum_in_stream = false;
+            let padding = ((8 - self.read_bits_count % 8) % 8) as usize;
+            if padding > 0{
+                self.read_bits(padding)?;
+            }
+            self.read_bits(32)? as u32
+        }
+        else if let Some(trailer) = self.trailer.take(){
+            let trailer = trailer.lock().map_err(|_| std::io::Error::other("trailer buffer is poisoned"))?;
+            let bytes: Vec<u8> = trailer.iter().copied().collect();
+            u32::from_le_bytes(bytes.try_into().map_err(|_| ZwlError::UnexpectedEof)?)
+        }
+        else{
+            return Ok(());
+        };
+        if let Some(checksum) = self.checksum.take() && checksum.value() != expected{
+            return Err(ZwlError::ChecksumMismatch { expected, actual: checksum.value() });
+        }
+        Ok(())
+    }
+    fn read_bits(&mut self, count: usize) -> std::io::Result<u64> {
+        let bits = self.input.read_bits(count as u32)?;
+        self.read_bits_count += count as u64;
+        Ok(bits)
+    }
+    fn read_sequence(&mut self) -> Result<bool, ZwlError> {
+        if self.finished{
+            return Ok(false);
+        }
+        let end_code = self.dictionary.end_code();
+        let clear_code = self.dictionary.clear_code();
+        loop{
+            if self.dictionary.implicit_width{
+                // The encoder is one entry ahead unless this is the first code or the dictionary stopped growing
+                let pending = self.old_index.is_some() && !self.dictionary.is_full();
+                self.size_req = implicit_width(self.dictionary.len() + usize::from(pending));
+            }
+            let code = match self.read_bits(self.size_req){
+                Ok(code) => code,
+                // Without an end code the stream ends where the data does, any other error is passed on
+                Err(e) if end_code.is_some() || e.kind() != std::io::ErrorKind::UnexpectedEof => return Err(e.into()),
+                Err(_) => return Ok(false),
+            };
+            let index = usize::try_from(code).ok().and_then(|code| T::try_from(code).ok()).ok_or(ZwlError::UnsupportedWidth(self.size_req as u8))?;
+            if end_code.is_some() && index.try_into().ok() == end_code{
+                self.stats.codes += 1;
+                self.finished = true;
+                return Ok(false);
+            }
+            if clear_code.is_some() && index.try_into().ok() == clear_code{
+                self.stats.codes += 1;
+                self.stats.clears += 1;
+                self.dictionary.clear();
+                self.size_req = self.dictionary.required_bits();
+                self.old_index = None;
+                continue;
+            }
+            let Some(old_index) = self.old_index else {
+                // println!("TRAILING ONES: {}, index: {index:?}, size_req: {size_req}", index.trailing_ones());
+                // Without a preset only alphabet codes can come first
+                let code = index.try_into().unwrap_or(usize::MAX);
+                let sequence = self.dictionary.get_phrase(index).ok_or(ZwlError::InvalidCode(code))?;
+                self.dictionary.touch(index);
+                self.stats.codes += 1;
+                self.old_index = Some(index);
+                self.old_sequence = sequence;
+                return Ok(true);
+            };
+            if !self.dictionary.implicit_width && index.trailing_ones() == self.size_req{
+                if T::custom_size() == self.size_req{
+                    self.size_req = 9;
                 }
                 else{
-                    size_req += 1;
+                    self.size_req += 1;
                 }
-                result = readable.read_bits(size_req);
+                self.stats.width_escapes += 1;
                 continue;
             }
-            let a = self.dictionary.get_phrase(index);
+            let code = index.try_into().unwrap_or(usize::MAX);
+            let next_code = self.dictionary.next_code(old_index);
+            let a = if code == next_code { None } else { self.dictionary.get_phrase(index) };
             match a{
                 Some(sequence) => {
-                    output.write(&sequence)?;
-                    self.dictionary.push(&(sequence[0], self.old_index.unwrap()));
+                    self.dictionary.push(&(sequence[0], old_index));
                     self.old_index = Some(index);
                     self.old_sequence = sequence;
                 },
                 None => {
+                    if code != next_code{
+                        return Err(ZwlError::InvalidCode(code));
+                    }
                     let mut sequence = self.old_sequence.clone();
                     sequence.push(self.old_sequence[0]);
-                    output.write(&sequence)?;
-                    self.dictionary.push(&(self.old_sequence[0], self.old_index.unwrap()));
-                    self.old_index = Some(T::try_from(self.dictionary.len() - 1).unwrap());
+                    self.dictionary.push(&(self.old_sequence[0], old_index));
+                    self.old_index = Some(T::try_from(code).map_err(|_| ZwlError::InvalidCode(code))?);
                     self.old_sequence = sequence;
                 },
             }
-            result = readable.read_bits(size_req);
+            self.dictionary.touch(index);
+            self.stats.codes += 1;
+            return Ok(true);
         }
-        output.flush()?;
-        Ok(())
     }
 }
\ No newline at end of file
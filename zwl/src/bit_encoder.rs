@@ -1,108 +1,329 @@
 use std::{io::{Read, Write}, ops::Sub};
 
-use bit_writer_reader::bit_writter::BitWriter;
+use crate::{bit_io::BitWriter, block::{default_threads, parallel_map, read_block, write_end, write_frame, write_index, DEFAULT_BLOCK_SIZE}, checksum::Crc32, code_width::MIN_CODE_WIDTH, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError, header::ZwlHeader, preset::PresetDictionary, stats::{CodingStats, Counter}, traits::{TrailingOnesR, LeadingZerosR, RequiredBits}};
 
-use crate::{dictionary::{Dictionary, FilledBehaviour}, traits::{TrailingOnesR, LeadingZerosR, RequiredBits, ToBits}};
+// Input bytes between two compression ratio checks of the adaptive behaviour, as in ncompress
+const CHECK_GAP: u64 = 10_000;
 
 pub struct ZwlBitEncoder<T: TryInto<usize>, I: Read>{
     input: I,
     pub dictionary: Dictionary<T>,
-    sequence: Vec<u8>,
-    current_symbol: Option<u8>,
     index: Option<T>,
+    size_req: usize,
+    pub original_len: Option<u64>,
+    pub file_name: Option<String>,
+    pub checksum: bool,
+    // Compresses blocks of this many input bytes independently, on `threads` threads
+    pub block_size: Option<u32>,
+    pub threads: usize,
+    // Writes a block index after the blocks, implies block mode
+    pub seekable: bool,
+    crc: Crc32,
+    written_bits: u64,
+    read_bytes: u64,
+    checkpoint: u64,
+    ratio: u64,
+    stats: CodingStats,
 }
 
 
 
 impl<T, I> ZwlBitEncoder<T, I>
 where 
-    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + ToBits + crate::traits::CustomWriteSize
-    + for<'a> TryFrom<&'a [bool], Error: std::fmt::Debug>
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + crate::traits::CustomWriteSize
     + LeadingZerosR + TrailingOnesR + RequiredBits
     , //+ Add<T, Output = T> 
     I: Read{
-pub fn encode_headerless<O: Write>(&mut self, mut output: O) -> std::io::Result<()> {
+pub fn encode_headerless<O: Write>(&mut self, output: O) -> Result<(), ZwlError> {
+        self.stats = CodingStats::default();
+        self.encode_payload(output, false)
+    }
+    fn encode_payload<O: Write>(&mut self, mut output: O, with_trailer: bool) -> Result<(), ZwlError> {
         let mut writtable = BitWriter::new(&mut output);
         let mut buf = [0; 64];
-        let mut result = self.input.read(&mut buf);
-        let mut size_req = 9;
-        while let Ok(s) = result && s > 0{
-            for i in 0..s{
-            self.current_symbol = Some(buf[i]);
-            self.sequence.push(buf[i]);
-                let found = self.dictionary.find(&self.sequence);
-                match found{
-                    Some(found) => {
-                        self.index = Some(found);
-                    },
-                    None => {
-                        if let Some(t) = self.index{
-                            // println!("INDEX: {t:?}, {:?}, {:?}, req_t {}", t.bits_vec(), TryInto::<T>::try_into(t.bits_vec().as_slice()), t.required_bits());
-                            let mut target = t.bits_vec();
-                            if target.len() < size_req{
-                                // println!("len:{} ->{size_req}", target.len());
-                                let mut summary = vec![false; size_req - target.len()];
-                                target.append(&mut summary);
-                            }
-                            writtable.write_bits(&target)?;
-                        }
-                        self.dictionary.push(&(self.current_symbol.unwrap(), self.index.unwrap()));
-                        let new_required_bits = self.dictionary.required_bits();
-                        if size_req != new_required_bits{
-                            // println!("{size_req} -> {new_required_bits}");
-                            let output = (0..size_req).into_iter().map(|_| true).collect::<Vec<_>>();
-                            writtable.write_bits(&output)?;
-                            // let transf= T::try_from(output.as_slice()).unwrap();
-                            // println!("11111...: {transf:?}, 0s: {}, 1s: {}, {}", transf.leading_zeros(), transf.trailing_ones(), transf.trailing_ones() - size_req);
-                            // println!("{size_req} -> {new_required_bits}");
-                            size_req = new_required_bits;
-                        }
-                        self.sequence = vec![self.current_symbol.unwrap()];
-                        self.index = self.dictionary.find(&[self.current_symbol.unwrap()]);
-                    },
+        let mut s = self.input.read(&mut buf)?;
+        while s > 0{
+            self.push_bytes(&buf[..s], &mut writtable)?;
+            s = self.input.read(&mut buf)?;
+        }
+        self.finish_codes(&mut writtable)?;
+        if with_trailer{
+            self.write_trailer(&mut writtable)?;
+        }
+        writtable.finish()?;
+        self.stats.output_bytes = self.written_bits.div_ceil(8);
+        self.stats.dictionary_len = self.dictionary.len();
+        Ok(())
+    }
+    pub(crate) fn push_bytes<W: Write>(&mut self, bytes: &[u8], writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        self.crc.update(bytes);
+        self.read_bytes += bytes.len() as u64;
+        self.stats.input_bytes += bytes.len() as u64;
+        for &byte in bytes{
+            self.push_byte(byte, writtable)?;
+        }
+        Ok(())
+    }
+    fn push_byte<W: Write>(&mut self, byte: u8, writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        let found = match self.index{
+            Some(prefix) => self.dictionary.find_child(prefix, byte),
+            None => Some(T::from(byte)),
+        };
+        match found{
+            Some(found) => {
+                self.index = Some(found);
+            },
+            None => {
+                if let Some(t) = self.index{
+                    // println!("INDEX: {t:?}, {:?}, {:?}, req_t {}", t.bits_vec(), TryInto::<T>::try_into(t.bits_vec().as_slice()), t.required_bits());
+                    self.write_index(writtable, t)?;
+                    self.dictionary.touch(t);
+                    if self.should_clear(){
+                        self.write_clear(writtable)?;
+                    }
+                    else{
+                        self.dictionary.push(&(byte, t));
+                    }
                 }
-            }
-            result = self.input.read(&mut buf);
+                let new_required_bits = self.code_width();
+                if self.size_req != new_required_bits{
+                    // println!("{size_req} -> {new_required_bits}");
+                    if !self.dictionary.implicit_width{
+                        self.write_bits(writtable, u64::MAX, self.size_req)?;
+                        self.stats.width_escapes += 1;
+                    }
+                    self.size_req = new_required_bits;
+                }
+                self.index = Some(T::from(byte));
+            },
         }
-        // println!("ending index: {:?}; current symbol: {:?}", self.index, self.current_symbol);
-        if let Some(last_symb) = self.index{
-            let mut target = last_symb.bits_vec();
-            if target.len() < size_req{
-                let mut summary = vec![false; size_req - target.len()];
-                target.append(&mut summary);
-            }
-            writtable.write_bits(&target)?;
+        Ok(())
+    }
+    fn should_clear(&mut self) -> bool{
+        // A dictionary filled by its preset alone would be full again right after clearing
+        if self.dictionary.clear_code().is_none() || !self.dictionary.is_full() || self.dictionary.words.len() <= self.dictionary.preset_len(){
+            return false;
+        }
+        match self.dictionary.filled{
+            FilledBehaviour::Clear => true,
+            FilledBehaviour::Freeze | FilledBehaviour::Lru => false,
+            FilledBehaviour::Adaptive => {
+                if self.read_bytes < self.checkpoint{
+                    return false;
+                }
+                self.checkpoint = self.read_bytes + CHECK_GAP;
+                let ratio = (self.read_bytes << 8) / self.written_bits.max(1);
+                if ratio > self.ratio{
+                    self.ratio = ratio;
+                    false
+                }
+                else{
+                    self.ratio = 0;
+                    true
+                }
+            },
+        }
+    }
+    pub(crate) fn clear_dictionary<W: Write>(&mut self, writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        if self.dictionary.clear_code().is_none(){
+            return Err(std::io::Error::other("Stream was created without a clear code").into());
+        }
+        if let Some(t) = self.index.take(){
+            self.write_index(writtable, t)?;
+        }
+        self.write_clear(writtable)
+    }
+    fn write_clear<W: Write>(&mut self, writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        if let Some(clear_code) = self.dictionary.clear_code(){
+            let clear_code = T::try_from(clear_code).map_err(|_| ZwlError::InvalidCode(clear_code))?;
+            self.write_index(writtable, clear_code)?;
+            self.stats.clears += 1;
+            self.dictionary.clear();
+            self.size_req = self.code_width();
         }
-        writtable.output.flush()?;
         Ok(())
     }
-    pub fn encode<O: Write>(&mut self, mut output: O) -> std::io::Result<()> {
-        Self::write_header(&mut output, &self.dictionary.filled)?;
-        self.encode_headerless(output)?;
+    // Starts the dictionary with the preset phrases, the header then records the preset id
+    pub fn prime(&mut self, preset: &PresetDictionary){
+        self.dictionary.prime(preset);
+        self.size_req = self.code_width();
+    }
+    fn code_width(&self) -> usize{
+        if self.dictionary.implicit_width{
+            implicit_width(self.dictionary.len())
+        }
+        else{
+            self.dictionary.required_bits()
+        }
+    }
+    pub(crate) fn finish_codes<W: Write>(&mut self, writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        // println!("ending index: {:?}; current symbol: {:?}", self.index, self.current_symbol);
+        if let Some(last_symb) = self.index.take(){
+            self.write_index(writtable, last_symb)?;
+        }
+        if let Some(end_code) = self.dictionary.end_code(){
+            let end_code = T::try_from(end_code).map_err(|_| ZwlError::InvalidCode(end_code))?;
+            self.write_index(writtable, end_code)?;
+        }
         Ok(())
     }
-    pub fn write_header<O>(output: &mut O, dictionary_filled: &FilledBehaviour) -> std::io::Result<()> where O: Write  {
-        let bit_size = Self::header_bit_size();
-        output.write_all(&bit_size.to_be_bytes())?;
-        match dictionary_filled{
-            FilledBehaviour::Clear => output.write_all(&[0])?,
-            FilledBehaviour::Freeze => output.write_all(&[1])?,
+    pub(crate) fn write_trailer<W: Write>(&mut self, writtable: &mut BitWriter<W>) -> Result<(), ZwlError> {
+        if !self.checksum{
+            return Ok(());
         }
+        let padding = ((8 - self.written_bits % 8) % 8) as usize;
+        self.write_bits(writtable, 0, padding)?;
+        self.write_bits(writtable, u64::from(self.crc.value()), 32)?;
         Ok(())
     }
+    fn write_index<W: Write>(&mut self, writtable: &mut BitWriter<W>, index: T) -> std::io::Result<()> {
+        let code = index.try_into().map_err(|_| std::io::Error::other("Index does not fit into usize"))?;
+        self.stats.codes += 1;
+        self.write_bits(writtable, code as u64, self.size_req)
+    }
+    fn write_bits<W: Write>(&mut self, writtable: &mut BitWriter<W>, value: u64, count: usize) -> std::io::Result<()> {
+        self.written_bits += count as u64;
+        let value = if count < 64 { value & ((1 << count) - 1) } else { value };
+        writtable.write_bits(value, count as u32)
+    }
+    // Counters of the last `encode`, output bytes include the header and framing
+    pub fn stats(&self) -> CodingStats{
+        self.stats
+    }
+    pub fn header(&self) -> ZwlHeader{
+        let mut header = ZwlHeader::new(Self::header_bit_size(), self.dictionary.filled);
+        header.original_len = self.original_len;
+        header.file_name = self.file_name.clone();
+        header.checksum = self.checksum;
+        header.end_code = self.dictionary.end_code().is_some();
+        header.clear_code = self.dictionary.clear_code().is_some();
+        header.implicit_width = self.dictionary.implicit_width;
+        header.preset_id = self.dictionary.preset_id;
+        header.block_size = self.block_size();
+        header.seekable = self.seekable;
+        header
+    }
+    pub fn write_header<O>(&self, output: &mut O) -> std::io::Result<()> where O: Write  {
+        self.header().write_to(output)
+    }
+    fn block_size(&self) -> Option<u32>{
+        self.block_size.or(self.seekable.then_some(DEFAULT_BLOCK_SIZE))
+    }
+    // Encoder for one block that starts from the state this encoder was configured with
+    fn block_encoder<J: Read>(&self, input: J) -> ZwlBitEncoder<T, J>{
+        ZwlBitEncoder{
+            input,
+            dictionary: self.dictionary.clone(),
+            index: None,
+            size_req: self.size_req,
+            original_len: None,
+            file_name: None,
+            checksum: self.checksum,
+            block_size: None,
+            threads: 1,
+            seekable: false,
+            crc: Crc32::new(),
+            written_bits: 0,
+            read_bytes: 0,
+            checkpoint: CHECK_GAP,
+            ratio: 0,
+            stats: CodingStats::default(),
+        }
+    }
     pub fn header_bit_size() -> u8 {
         let bit_size: u8 = (T::custom_size() as usize).try_into().unwrap();
         bit_size
     }
     pub fn new(input: I, dictionary_filled: FilledBehaviour) -> Self{
+        let mut encoder = Self::new_legacy(input, dictionary_filled);
+        encoder.dictionary.reserved = 2;
+        encoder.checksum = true;
+        encoder.dictionary.implicit_width = true;
+        encoder
+    }
+    pub fn new_legacy(input: I, dictionary_filled: FilledBehaviour) -> Self{
         let mut dictionary = Dictionary::default();
         dictionary.filled= dictionary_filled;
         Self{
             input,
             dictionary,
-            sequence: vec![],
-            current_symbol: None,
-            index: None
+            index: None,
+            size_req: 9,
+            original_len: None,
+            file_name: None,
+            checksum: false,
+            block_size: None,
+            threads: default_threads(),
+            seekable: false,
+            crc: Crc32::new(),
+            written_bits: 0,
+            read_bytes: 0,
+            checkpoint: CHECK_GAP,
+            ratio: 0,
+            stats: CodingStats::default(),
         }
     }
+}
+
+
+impl<T, I> ZwlBitEncoder<T, I>
+where 
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + crate::traits::CustomWriteSize
+    + LeadingZerosR + TrailingOnesR + RequiredBits + Send + Sync,
+    I: Read{
+    pub fn encode<O: Write>(&mut self, output: O) -> Result<(), ZwlError> {
+        self.stats = CodingStats::default();
+        let header = self.header();
+        header.check_codes()?;
+        let mut output = Counter::new(output);
+        header.write_to(&mut output)?;
+        match self.block_size(){
+            Some(block_size) => self.encode_blocks(&mut output, block_size)?,
+            None => self.encode_payload(&mut output, true)?,
+        }
+        self.stats.output_bytes = output.count();
+        Ok(())
+    }
+    fn encode_blocks<O: Write>(&mut self, mut output: O, block_size: u32) -> Result<(), ZwlError> {
+        let block_size = block_size.max(1) as usize;
+        let template = self.block_encoder(std::io::empty());
+        let input = &mut self.input;
+        let mut index = vec![];
+        let stats = &mut self.stats;
+        let jobs = std::iter::from_fn(|| {
+            let mut block = vec![0; block_size];
+            match read_block(input, &mut block){
+                Ok(0) => None,
+                Ok(len) => {
+                    block.truncate(len);
+                    Some(Ok(block))
+                },
+                Err(e) => Some(Err(ZwlError::from(e))),
+            }
+        });
+        parallel_map(self.threads, jobs,
+            |block: Vec<u8>| {
+                let mut payload = vec![];
+                let mut encoder = template.block_encoder(&block[..]);
+                encoder.encode_payload(&mut payload, true)?;
+                Ok((block.len(), payload, encoder.stats))
+            },
+            |(original_len, payload, block_stats)| {
+                write_frame(&mut output, original_len, &payload)?;
+                stats.merge(&block_stats);
+                index.push((payload.len() as u32, original_len as u32));
+                Ok(())
+            })?;
+        write_end(&mut output)?;
+        if self.seekable{
+            write_index(&mut output, &index)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+// Enough bits for the highest code of a dictionary with `dictionary_len` entries, but never below 9
+pub(crate) fn implicit_width(dictionary_len: usize) -> usize{
+    ((usize::BITS - dictionary_len.saturating_sub(1).leading_zeros()) as usize).max(MIN_CODE_WIDTH as usize)
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+use std::io::{Read, Write};
+
+const BUFFER_LEN: usize = 8192;
+
+// Packs codes LSB-first: the first bit of a code goes into the lowest free bit of the current byte
+pub struct BitWriter<W: Write>{
+    pub output: W,
+    accumulator: u64,
+    filled: u32,
+    bytes: Vec<u8>,
+}
+
+impl<W: Write> BitWriter<W>{
+    pub fn new(output: W) -> Self{
+        Self { output, accumulator: 0, filled: 0, bytes: Vec::with_capacity(BUFFER_LEN) }
+    }
+    pub fn write_bits(&mut self, mut value: u64, mut count: u32) -> std::io::Result<()>{
+        while count > 0{
+            let taken = count.min(u64::BITS - self.filled);
+            let part = if taken == u64::BITS { value } else { value & ((1 << taken) - 1) };
+            self.accumulator |= part << self.filled;
+            self.filled += taken;
+            value = value.checked_shr(taken).unwrap_or(0);
+            count -= taken;
+            if self.filled == u64::BITS{
+                self.bytes.extend_from_slice(&self.accumulator.to_le_bytes());
+                self.accumulator = 0;
+                self.filled = 0;
+                if self.bytes.len() >= BUFFER_LEN{
+                    self.output.write_all(&self.bytes)?;
+                    self.bytes.clear();
+                }
+            }
+        }
+        Ok(())
+    }
+    // Writes every complete byte, the unfinished one stays in the accumulator
+    pub fn flush(&mut self) -> std::io::Result<()>{
+        let complete = (self.filled / 8) as usize;
+        self.bytes.extend_from_slice(&self.accumulator.to_le_bytes()[..complete]);
+        self.accumulator = self.accumulator.checked_shr(complete as u32 * 8).unwrap_or(0);
+        self.filled -= complete as u32 * 8;
+        self.output.write_all(&self.bytes)?;
+        self.bytes.clear();
+        self.output.flush()
+    }
+    // Pads the last byte with zeros and writes everything out
+    pub fn finish(&mut self) -> std::io::Result<()>{
+        if !self.filled.is_multiple_of(8){
+            self.write_bits(0, 8 - self.filled % 8)?;
+        }
+        self.flush()
+    }
+}
+
+impl<W: Write> Drop for BitWriter<W>{
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+pub struct BitReader<R: Read>{
+    pub input: R,
+    accumulator: u64,
+    available: u32,
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> BitReader<R>{
+    pub fn new(input: R) -> Self{
+        Self { input, accumulator: 0, available: 0, bytes: vec![], position: 0 }
+    }
+    pub fn read_bits(&mut self, count: u32) -> std::io::Result<u64>{
+        if count > 56{
+            let low = self.read_bits(32)?;
+            let high = self.read_bits(count - 32)?;
+            return Ok(low | (high << 32));
+        }
+        while self.available < count{
+            let byte = next_byte(&mut self.input, &mut self.bytes, &mut self.position)?;
+            self.accumulator |= u64::from(byte) << self.available;
+            self.available += 8;
+        }
+        let value = self.accumulator & ((1 << count) - 1);
+        self.accumulator >>= count;
+        self.available -= count;
+        Ok(value)
+    }
+    // Drops the padding of the current byte and returns the bytes read ahead together with the input
+    pub fn into_remainder(self) -> (Vec<u8>, R){
+        let padding = self.available % 8;
+        let mut accumulator = self.accumulator >> padding;
+        let mut rest = Vec::with_capacity((self.available / 8) as usize + self.bytes.len() - self.position);
+        for _ in 0..self.available / 8{
+            rest.push(accumulator as u8);
+            accumulator >>= 8;
+        }
+        rest.extend_from_slice(&self.bytes[self.position..]);
+        (rest, self.input)
+    }
+}
+
+fn next_byte<R: Read>(input: &mut R, bytes: &mut Vec<u8>, position: &mut usize) -> std::io::Result<u8>{
+    if *position == bytes.len(){
+        bytes.resize(BUFFER_LEN, 0);
+        let count = loop{
+            match input.read(bytes){
+                Ok(count) => break count,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    bytes.clear();
+                    *position = 0;
+                    return Err(e);
+                },
+            }
+        };
+        bytes.truncate(count);
+        *position = 0;
+        if count == 0{
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+    *position += 1;
+    Ok(bytes[*position - 1])
+}
+
+// Packs codes MSB-first like TIFF and PDF: the first bit of a code is the highest bit of the current byte
+pub struct MsbBitWriter<W: Write>{
+    pub output: W,
+    accumulator: u64,
+    filled: u32,
+    bytes: Vec<u8>,
+}
+
+impl<W: Write> MsbBitWriter<W>{
+    pub fn new(output: W) -> Self{
+        Self { output, accumulator: 0, filled: 0, bytes: Vec::with_capacity(BUFFER_LEN) }
+    }
+    pub fn write_bits(&mut self, value: u64, count: u32) -> std::io::Result<()>{
+        if count > 32{
+            self.write_bits(value >> 32, count - 32)?;
+            return self.write_bits(value & u64::from(u32::MAX), 32);
+        }
+        self.accumulator = (self.accumulator << count) | (value & ((1 << count) - 1));
+        self.filled += count;
+        while self.filled >= 8{
+            self.filled -= 8;
+            self.bytes.push((self.accumulator >> self.filled) as u8);
+        }
+        self.accumulator &= (1 << self.filled) - 1;
+        if self.bytes.len() >= BUFFER_LEN{
+            self.output.write_all(&self.bytes)?;
+            self.bytes.clear();
+        }
+        Ok(())
+    }
+    // Writes every complete byte, the unfinished one stays in the accumulator
+    pub fn flush(&mut self) -> std::io::Result<()>{
+        self.output.write_all(&self.bytes)?;
+        self.bytes.clear();
+        self.output.flush()
+    }
+    // Pads the last byte with zeros and writes everything out
+    pub fn finish(&mut self) -> std::io::Result<()>{
+        if self.filled > 0{
+            self.write_bits(0, 8 - self.filled)?;
+        }
+        self.flush()
+    }
+}
+
+impl<W: Write> Drop for MsbBitWriter<W>{
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+pub struct MsbBitReader<R: Read>{
+    pub input: R,
+    accumulator: u64,
+    available: u32,
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> MsbBitReader<R>{
+    pub fn new(input: R) -> Self{
+        Self { input, accumulator: 0, available: 0, bytes: vec![], position: 0 }
+    }
+    pub fn read_bits(&mut self, count: u32) -> std::io::Result<u64>{
+        if count > 32{
+            let high = self.read_bits(count - 32)?;
+            let low = self.read_bits(32)?;
+            return Ok((high << 32) | low);
+        }
+        while self.available < count{
+            let byte = next_byte(&mut self.input, &mut self.bytes, &mut self.position)?;
+            self.accumulator = (self.accumulator << 8) | u64::from(byte);
+            self.available += 8;
+        }
+        self.available -= count;
+        let value = (self.accumulator >> self.available) & ((1 << count) - 1);
+        self.accumulator &= (1 << self.available) - 1;
+        Ok(value)
+    }
+}
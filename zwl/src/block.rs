@@ -0,0 +1,235 @@
+use std::{collections::BTreeMap, io::{Read, Write}, sync::{mpsc, Mutex}};
+
+use crate::{auto_decoder::{new_decoder, DecodeOptions, ZwlDecoderE}, error::ZwlError, header::ZwlHeader, stats::CodingStats, with_code_width};
+
+pub const DEFAULT_BLOCK_SIZE: u32 = 1 << 20;
+pub const INDEX_MAGIC: [u8; 4] = *b"ZWLX";
+// Block count and magic at the very end of a seekable stream
+pub const INDEX_TRAILER_LEN: usize = 8;
+pub(crate) const FRAME_HEADER_LEN: u64 = 8;
+
+pub fn default_threads() -> usize{
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+// Runs `work` for every job on `threads` workers and hands the results to `sink` in job order
+pub(crate) fn parallel_map<J, R, E>(threads: usize, jobs: impl Iterator<Item = Result<J, E>>, work: impl Fn(J) -> Result<R, E> + Sync, mut sink: impl FnMut(R) -> Result<(), E>) -> Result<(), E>
+where J: Send, R: Send, E: Send + From<std::io::Error>{
+    let threads = threads.max(1);
+    // Bounded so that only a few blocks are read ahead of the workers
+    let (job_sender, job_receiver) = mpsc::sync_channel::<(usize, J)>(threads);
+    let job_receiver = Mutex::new(job_receiver);
+    let (result_sender, result_receiver) = mpsc::channel::<(usize, Result<R, E>)>();
+    std::thread::scope(|scope| {
+        for _ in 0..threads{
+            let job_receiver = &job_receiver;
+            let result_sender = result_sender.clone();
+            let work = &work;
+            scope.spawn(move || {
+                loop{
+                    let job = match job_receiver.lock(){
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    let Ok((number, job)) = job else {
+                        break;
+                    };
+                    if result_sender.send((number, work(job))).is_err(){
+                        break;
+                    }
+                }
+            });
+        }
+        drop(result_sender);
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let mut sent = 0;
+        for job in jobs{
+            if job_sender.send((sent, job?)).is_err(){
+                return Err(std::io::Error::other("Block workers stopped").into());
+            }
+            sent += 1;
+            while let Ok((number, result)) = result_receiver.try_recv(){
+                pending.insert(number, result);
+            }
+            while let Some(result) = pending.remove(&next){
+                sink(result?)?;
+                next += 1;
+            }
+        }
+        drop(job_sender);
+        while next < sent{
+            let Ok((number, result)) = result_receiver.recv() else {
+                return Err(std::io::Error::other("Block worker stopped before its block was done").into());
+            };
+            pending.insert(number, result);
+            while let Some(result) = pending.remove(&next){
+                sink(result?)?;
+                next += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+// Reads until `block` is full or the input ends, returns the number of bytes read
+pub(crate) fn read_block<I: Read>(input: &mut I, block: &mut [u8]) -> std::io::Result<usize>{
+    let mut filled = 0;
+    while filled < block.len(){
+        match input.read(&mut block[filled..]){
+            Ok(0) => break,
+            Ok(count) => filled += count,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// Each block is framed by its compressed and original length, a zero compressed length ends the blocks
+pub(crate) fn write_frame<O: Write>(output: &mut O, original_len: usize, payload: &[u8]) -> std::io::Result<()>{
+    let compressed_len = u32::try_from(payload.len()).map_err(|_| std::io::Error::other("Compressed block is too long"))?;
+    let original_len = u32::try_from(original_len).map_err(|_| std::io::Error::other("Block is too long"))?;
+    output.write_all(&compressed_len.to_le_bytes())?;
+    output.write_all(&original_len.to_le_bytes())?;
+    output.write_all(payload)
+}
+
+pub(crate) fn write_end<O: Write>(output: &mut O) -> std::io::Result<()>{
+    output.write_all(&0u32.to_le_bytes())
+}
+
+// Compressed and original length of every block, the count is repeated at the end for readers that seek
+pub(crate) fn write_index<O: Write>(output: &mut O, index: &[(u32, u32)]) -> std::io::Result<()>{
+    let count = u32::try_from(index.len()).map_err(|_| std::io::Error::other("Too many blocks for the block index"))?;
+    output.write_all(&count.to_le_bytes())?;
+    for (compressed_len, original_len) in index{
+        output.write_all(&compressed_len.to_le_bytes())?;
+        output.write_all(&original_len.to_le_bytes())?;
+    }
+    output.write_all(&count.to_le_bytes())?;
+    output.write_all(&INDEX_MAGIC)
+}
+
+pub(crate) fn read_index_trailer(trailer: [u8; INDEX_TRAILER_LEN]) -> Result<u32, ZwlError>{
+    let [count @ .., m0, m1, m2, m3] = trailer;
+    if [m0, m1, m2, m3] != INDEX_MAGIC{
+        return Err(ZwlError::BadHeader("Block index magic does not match".into()));
+    }
+    Ok(u32::from_le_bytes(count))
+}
+
+fn skip_index<I: Read>(input: &mut I) -> Result<(), ZwlError>{
+    let mut count = [0; 4];
+    input.read_exact(&mut count)?;
+    let entries = u64::from(u32::from_le_bytes(count)) * 8;
+    if std::io::copy(&mut input.take(entries), &mut std::io::sink())? != entries{
+        return Err(ZwlError::UnexpectedEof);
+    }
+    let mut trailer = [0; INDEX_TRAILER_LEN];
+    input.read_exact(&mut trailer)?;
+    if read_index_trailer(trailer)? != u32::from_le_bytes(count){
+        return Err(ZwlError::BadHeader("Block index count does not match".into()));
+    }
+    Ok(())
+}
+
+fn read_frame<I: Read>(input: &mut I) -> Result<Option<(usize, Vec<u8>)>, ZwlError>{
+    let mut compressed_len = [0; 4];
+    input.read_exact(&mut compressed_len)?;
+    let compressed_len = u32::from_le_bytes(compressed_len) as usize;
+    if compressed_len == 0{
+        return Ok(None);
+    }
+    let mut original_len = [0; 4];
+    input.read_exact(&mut original_len)?;
+    Ok(Some((u32::from_le_bytes(original_len) as usize, read_payload(input, compressed_len)?)))
+}
+
+// Grows the buffer while reading, so a broken length does not allocate gigabytes up front
+pub(crate) fn read_payload<I: Read>(input: &mut I, len: usize) -> Result<Vec<u8>, ZwlError>{
+    let mut payload = vec![];
+    input.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len{
+        return Err(ZwlError::UnexpectedEof);
+    }
+    Ok(payload)
+}
+
+pub(crate) fn decode_block(original_len: usize, payload: &[u8], header: &ZwlHeader, options: &DecodeOptions) -> Result<(Vec<u8>, CodingStats), ZwlError>{
+    let mut decoder: ZwlDecoderE<&[u8]> = with_code_width!(header.bit_size, W => new_decoder::<W, _>(payload, header, options).into(),
+        _ => return Err(ZwlError::UnsupportedWidth(header.bit_size)));
+    if original_len > header.block_size.unwrap_or(u32::MAX) as usize{
+        return Err(std::io::Error::other(format!("Block of {original_len} bytes is longer than the block size")).into());
+    }
+    // Both lengths come from the stream, so only as much is reserved as the payload could plausibly hold
+    let mut output = Vec::with_capacity(original_len.min(payload.len().saturating_mul(8)));
+    decoder.decode(&mut output)?;
+    if output.len() != original_len{
+        return Err(std::io::Error::other(format!("Block decoded to {} bytes instead of {original_len}", output.len())).into());
+    }
+    Ok((output, decoder.stats()))
+}
+
+// Decodes the independent blocks of a stream written in block mode
+pub struct ZwlBlockDecoder<I: Read>{
+    input: I,
+    header: ZwlHeader,
+    options: DecodeOptions,
+    buffer: Vec<u8>,
+    finished: bool,
+    stats: CodingStats,
+}
+
+impl<I: Read> ZwlBlockDecoder<I>{
+    pub fn new(input: I, header: ZwlHeader, options: DecodeOptions) -> Self{
+        Self { input, header, options, buffer: vec![], finished: false, stats: CodingStats::default() }
+    }
+    pub fn stats(&self) -> CodingStats{
+        self.stats
+    }
+    // Decodes `options.threads` blocks at once
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        let Self { input, header, options, finished, stats, .. } = self;
+        if !*finished{
+            let jobs = std::iter::from_fn(|| read_frame(input).transpose());
+            parallel_map(options.threads, jobs,
+                |(original_len, payload)| decode_block(original_len, &payload, header, options),
+                |(block, block_stats)| {
+                    stats.merge(&block_stats);
+                    Ok(output.write_all(&block)?)
+                })?;
+            if header.seekable{
+                skip_index(input)?;
+            }
+            *finished = true;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn into_input(self) -> I{
+        self.input
+    }
+    // Decodes one block at a time on the calling thread
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        while !self.finished{
+            match read_frame(&mut self.input)?{
+                Some((original_len, payload)) => {
+                    let (block, block_stats) = decode_block(original_len, &payload, &self.header, &self.options)?;
+                    self.buffer = block;
+                    self.stats.merge(&block_stats);
+                    if !self.buffer.is_empty(){
+                        return Ok(Some(&self.buffer));
+                    }
+                },
+                None => {
+                    if self.header.seekable{
+                        skip_index(&mut self.input)?;
+                    }
+                    self.finished = true;
+                },
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::VecDeque, io::Read, sync::{Arc, Mutex}};
+
+pub const CHECKSUM_LEN: usize = 4;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256]{
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256{
+        let mut value = i as u32;
+        let mut bit = 0;
+        while bit < 8{
+            value = if value & 1 == 1 { (value >> 1) ^ 0xEDB8_8320 } else { value >> 1 };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32{
+    state: u32,
+}
+
+impl Crc32{
+    pub fn new() -> Self{
+        Self { state: u32::MAX }
+    }
+    pub fn update(&mut self, bytes: &[u8]){
+        for &byte in bytes{
+            self.state = CRC32_TABLE[((self.state ^ u32::from(byte)) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+    pub fn value(&self) -> u32{
+        !self.state
+    }
+}
+
+impl Default for Crc32{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) type Trailer = Arc<Mutex<VecDeque<u8>>>;
+
+// Keeps the last `hold` bytes of the input away from the bit reader, so the
+// trailer written after the bit stream is not decoded as codes.
+pub(crate) struct TrailerReader<I: Read>{
+    input: I,
+    hold: usize,
+    held: Trailer,
+}
+
+impl<I: Read> TrailerReader<I>{
+    pub(crate) fn new(input: I, hold: usize) -> Self{
+        Self { input, hold, held: Arc::new(Mutex::new(VecDeque::new())) }
+    }
+    pub(crate) fn trailer(&self) -> Trailer{
+        self.held.clone()
+    }
+    pub(crate) fn into_inner(self) -> I{
+        self.input
+    }
+}
+
+impl<I: Read> Read for TrailerReader<I>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.hold == 0{
+            return self.input.read(buf);
+        }
+        let mut held = self.held.lock().map_err(|_| std::io::Error::other("trailer buffer is poisoned"))?;
+        let mut chunk = [0; 4096];
+        while held.len() <= self.hold{
+            let count = self.input.read(&mut chunk)?;
+            if count == 0{
+                return Ok(0);
+            }
+            held.extend(&chunk[..count]);
+        }
+        let count = (held.len() - self.hold).min(buf.len());
+        for (target, byte) in buf.iter_mut().zip(held.drain(..count)){
+            *target = byte;
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,125 @@
+use std::ops::Sub;
+
+use crate::traits::{CustomWriteSize, TrailingOnesR, LeadingZerosR, RequiredBits, ToBits};
+
+pub const MIN_CODE_WIDTH: u8 = 9;
+pub const MAX_CODE_WIDTH: u8 = 64;
+
+#[derive(Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct CodeWidth<const BITS: u32>(pub u64);
+
+pub type LikeU12 = CodeWidth<12>;
+pub type LikeU16 = CodeWidth<16>;
+pub type LikeU32 = CodeWidth<32>;
+pub type LikeU64 = CodeWidth<64>;
+
+impl<const BITS: u32> CodeWidth<BITS>{
+    // Evaluated when a width is used, so CodeWidth<8> or CodeWidth<65> fail to compile
+    const VALID: () = assert!(BITS >= MIN_CODE_WIDTH as u32 && BITS <= MAX_CODE_WIDTH as u32, "code width must be between 9 and 64 bits");
+    const MASK: u64 = if BITS == 64 { u64::MAX } else { (1 << BITS) - 1 };
+}
+
+impl<const BITS: u32> TryFrom<usize> for CodeWidth<BITS>{
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        let internal = u64::try_from(value)?;
+        if internal > Self::MASK {
+            return Err(u8::try_from(402).unwrap_err());
+        }
+        Ok(Self(internal))
+    }
+}
+
+impl<const BITS: u32> TryFrom<CodeWidth<BITS>> for usize{
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: CodeWidth<BITS>) -> Result<Self, Self::Error> {
+        value.0.try_into()
+    }
+}
+
+impl<const BITS: u32> ToBits for CodeWidth<BITS>{
+    fn bits_vec(&self) -> Vec<bool> {
+        (0..self.required_bits()).map(|position| (self.0 >> position) & 1 == 1).collect()
+    }
+}
+impl<const BITS: u32> CustomWriteSize for CodeWidth<BITS>{
+    fn custom_size() -> usize {
+        let () = Self::VALID;
+        BITS as usize
+    }
+}
+
+impl<const BITS: u32> From<u8> for CodeWidth<BITS>{
+    fn from(value: u8) -> Self {
+        Self(u64::from(value))
+    }
+}
+impl<const BITS: u32> min_max_traits::Max for CodeWidth<BITS>{
+    const MAX: Self = Self(Self::MASK);
+}
+impl<const BITS: u32> std::fmt::Debug for CodeWidth<BITS>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl<const BITS: u32> Sub<CodeWidth<BITS>> for CodeWidth<BITS>{
+    type Output = CodeWidth<BITS>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+impl<const BITS: u32> TryFrom<&[bool]> for CodeWidth<BITS>{
+    type Error = String;
+
+    fn try_from(value: &[bool]) -> Result<Self, Self::Error> {
+        if value.len() > Self::custom_size(){
+            return Err(format!("Requires {} bits but {} were provided", Self::custom_size(), value.len()));
+        }
+        let mut internal = 0;
+        value.iter().enumerate().for_each(|(i, b)| {
+            if *b{
+                internal |= 1u64 << i
+            }
+        });
+        Ok(Self(internal))
+    }
+}
+
+impl<const BITS: u32> RequiredBits for CodeWidth<BITS>{
+    fn required_bits(&self) -> usize{
+        (u64::BITS - self.0.leading_zeros()) as usize
+    }
+}
+impl<const BITS: u32> LeadingZerosR for CodeWidth<BITS>{
+    fn leading_zeros(&self) -> usize {
+        (self.0.leading_zeros() - (u64::BITS - BITS)) as usize
+    }
+}
+
+impl<const BITS: u32> TrailingOnesR for CodeWidth<BITS>{
+    fn trailing_ones(&self) -> usize {
+        self.0.trailing_ones() as usize
+    }
+}
+
+// Expands `$body` once per supported width with `$width` naming the matching CodeWidth
+#[macro_export]
+macro_rules! with_code_width{
+    ($bits:expr, $width:ident => $body:expr, _ => $otherwise:expr) => {
+        $crate::with_code_width!(@arms $bits, $width, $body, $otherwise;
+            9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36
+            37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64)
+    };
+    (@arms $bits:expr, $width:ident, $body:expr, $otherwise:expr; $($width_bits:literal)*) => {
+        match $bits{
+            $($width_bits => {
+                type $width = $crate::code_width::CodeWidth<$width_bits>;
+                $body
+            })*
+            _ => $otherwise,
+        }
+    };
+}
@@ -1,21 +1,85 @@
 use std::ops::{Index, IndexMut, Sub};
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
-use crate::traits::RequiredBits;
+use crate::{preset::PresetDictionary, traits::RequiredBits};
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilledBehaviour{
     Clear,
-    Freeze
+    Freeze,
+    Adaptive,
+    Lru
 }
 
+#[derive(Clone)]
 pub struct Dictionary<T>{
-    pub alphabet:[(u8, Option<T>); 256],
+    pub alphabet: Vec<(u8, Option<T>)>,
     pub words: Vec<(u8, Option<T>)>,
-    pub filled: FilledBehaviour
+    pub filled: FilledBehaviour,
+    pub reserved: usize,
+    // Width grows from the dictionary size, so the all-ones code is no longer needed as a width escape
+    pub implicit_width: bool,
+    // CRC-32 id of the preset the dictionary was primed with
+    pub preset_id: Option<u32>,
+    preset: Option<PresetWords<T>>,
+    children: HashMap<(usize, u8), usize>,
+    // Last use and child count of every word, only tracked for FilledBehaviour::Lru
+    usage: Vec<(u64, usize)>,
+    leaves: BTreeSet<(u64, usize)>,
+    tick: u64,
+}
+
+// Words added by priming, a cleared dictionary starts from them again
+#[derive(Clone)]
+struct PresetWords<T>{
+    words: Vec<(u8, Option<T>)>,
+    children: HashMap<(usize, u8), usize>,
+    usage: Vec<(u64, usize)>,
+    leaves: BTreeSet<(u64, usize)>,
 }
 
 impl<T> Dictionary<T>{
+    // Alphabet of the first `size` byte values, GIF uses 2^n symbols for a minimum code size n
+    pub fn with_alphabet(size: usize) -> Self{
+        let alphabet = (0..size.min(256)).map(|byte| (byte as u8, None)).collect();
+        Self { alphabet, words: vec![], filled: FilledBehaviour::Clear, reserved: 0, implicit_width: false, preset_id: None, preset: None, children: HashMap::new(), usage: vec![], leaves: BTreeSet::new(), tick: 0 }
+    }
     pub fn len(&self) -> usize{
-        self.alphabet.len() + self.words.len()
+        self.alphabet.len() + self.reserved + self.words.len()
+    }
+    pub fn reserved_code(&self, position: usize) -> usize{
+        self.alphabet.len() + position
+    }
+    pub fn end_code(&self) -> Option<usize>{
+        (self.reserved > 0).then(|| self.reserved_code(0))
+    }
+    pub fn clear_code(&self) -> Option<usize>{
+        (self.reserved > 1).then(|| self.reserved_code(1))
+    }
+    pub fn preset_len(&self) -> usize{
+        self.preset.as_ref().map_or(0, |preset| preset.words.len())
+    }
+    fn word_position(&self, code: usize) -> Option<usize>{
+        code.checked_sub(self.reserved_code(self.reserved)).filter(|position| *position < self.words.len())
+    }
+    pub fn lru_victim(&self, prefix: usize) -> Option<usize>{
+        let first_word = self.reserved_code(self.reserved);
+        self.leaves.iter().map(|(_, position)| first_word + position).find(|code| *code != prefix)
+    }
+    fn add_child(&mut self, code: usize){
+        if let Some(position) = self.word_position(code){
+            if self.usage[position].1 == 0{
+                self.leaves.remove(&(self.usage[position].0, position));
+            }
+            self.usage[position].1 += 1;
+        }
+    }
+    fn remove_child(&mut self, code: usize){
+        if let Some(position) = self.word_position(code){
+            self.usage[position].1 -= 1;
+            if self.usage[position].1 == 0{
+                self.leaves.insert((self.usage[position].0, position));
+            }
+        }
     }
 }
 impl<T> RequiredBits for Dictionary<T>{
@@ -23,73 +87,144 @@ impl<T> RequiredBits for Dictionary<T>{
         std::mem::size_of::<usize>() * 8 - self.len().leading_zeros() as usize
     }
 }
+impl<T: Clone> Dictionary<T>{
+    pub fn clear(&mut self){
+        match &self.preset{
+            Some(preset) => {
+                self.words.clone_from(&preset.words);
+                self.children.clone_from(&preset.children);
+                self.usage.clone_from(&preset.usage);
+                self.leaves.clone_from(&preset.leaves);
+            },
+            None => {
+                self.words = vec![];
+                self.children.clear();
+                self.usage = vec![];
+                self.leaves.clear();
+            },
+        }
+    }
+}
 impl<T: Copy + TryInto<usize, Error: std::fmt::Debug> + min_max_traits::Max> Dictionary<T>{
+    pub fn is_full(&self) -> bool{
+        let max: usize = (T::MAX).try_into().unwrap();
+        self.len() >= if self.implicit_width { max.saturating_add(1) } else { max }
+    }
+    pub fn next_code(&self, prefix: T) -> usize{
+        match (self.filled, prefix.try_into()){
+            (FilledBehaviour::Lru, Ok(prefix)) if self.is_full() => self.lru_victim(prefix).unwrap_or(self.len()),
+            _ => self.len(),
+        }
+    }
+    pub fn touch(&mut self, code: T){
+        if self.filled != FilledBehaviour::Lru{
+            return;
+        }
+        let Some(position) = code.try_into().ok().and_then(|code| self.word_position(code)) else {
+            return;
+        };
+        let (last_used, children) = self.usage[position];
+        if children == 0{
+            self.leaves.remove(&(last_used, position));
+            self.leaves.insert((self.tick, position));
+        }
+        self.usage[position].0 = self.tick;
+        self.tick += 1;
+    }
     pub fn push(&mut self, word: &(u8, T)){
-        if self.len() >= (T::MAX).try_into().unwrap(){
+        if self.is_full(){
             // println!("Reached max!");
             match self.filled{
-                FilledBehaviour::Freeze => return,
-                FilledBehaviour::Clear => self.words = vec![],
+                // Adaptive resets only happen when the encoder sends a clear code
+                FilledBehaviour::Freeze | FilledBehaviour::Adaptive => return,
+                FilledBehaviour::Clear => self.clear(),
+                FilledBehaviour::Lru => self.replace_lru(word),
             }
             return;
         }
+        let code = self.len();
+        let prefix = word.1.try_into().unwrap();
+        self.children.insert((prefix, word.0), code);
         self.words.push((word.0, Some(word.1)));
+        if self.filled == FilledBehaviour::Lru{
+            self.usage.push((self.tick, 0));
+            self.leaves.insert((self.tick, self.words.len() - 1));
+            self.tick += 1;
+            self.add_child(prefix);
+        }
+    }
+    fn replace_lru(&mut self, word: &(u8, T)){
+        let prefix = word.1.try_into().unwrap();
+        let Some(code) = self.lru_victim(prefix) else {
+            return;
+        };
+        let position = code - self.reserved_code(self.reserved);
+        let (old_byte, old_prefix) = self.words[position];
+        if let Some(old_prefix) = old_prefix{
+            let old_prefix = old_prefix.try_into().unwrap();
+            if self.children.get(&(old_prefix, old_byte)) == Some(&code){
+                self.children.remove(&(old_prefix, old_byte));
+            }
+            self.remove_child(old_prefix);
+        }
+        self.leaves.remove(&(self.usage[position].0, position));
+        self.words[position] = (word.0, Some(word.1));
+        self.children.insert((prefix, word.0), code);
+        self.usage[position] = (self.tick, 0);
+        self.leaves.insert((self.tick, position));
+        self.tick += 1;
+        self.add_child(prefix);
     }
 }
 impl<T: From<u8> + PartialOrd + Copy + Sub<T, Output = T> + TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + std::fmt::Debug> Dictionary<T>{
     pub fn find(&self, searched: &[u8]) -> Option<T>{
-        let sequence_len = searched.len();
-        if sequence_len == 1{
-            if searched[0] == self[searched[0]].0{
-                return Some(searched[0].into());
-            }
-            println!("weird");
+        let (first, rest) = searched.split_first()?;
+        let mut code = usize::from(*first);
+        if code >= self.alphabet.len(){
             return None;
         }
-        else{
-            let mut candidates = vec![];
-            for j in (u8::MAX - 1) as usize .. (self.len()){
-                if searched[sequence_len - 1] == self[j].0 && let Some(next) = self[j].1{
-                    candidates.push((j, next));
-                }
+        for byte in rest{
+            code = *self.children.get(&(code, *byte))?;
+        }
+        T::try_from(code).ok()
+    }
+    pub fn find_child(&self, prefix: T, byte: u8) -> Option<T>{
+        let code = self.children.get(&(prefix.try_into().ok()?, byte))?;
+        T::try_from(*code).ok()
+    }
+}
+impl<T: From<u8> + PartialOrd + Copy + Sub<T, Output = T> + TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + std::fmt::Debug + min_max_traits::Max> Dictionary<T>{
+    // Adds the preset phrases with all their prefixes, phrases that no longer fit are left out
+    pub fn prime(&mut self, preset: &PresetDictionary){
+        self.preset = None;
+        self.clear();
+        'phrases: for phrase in &preset.phrases{
+            let Some((first, rest)) = phrase.split_first() else {
+                continue;
+            };
+            if usize::from(*first) >= self.alphabet.len(){
+                continue;
             }
-            let candidates: Vec<_> = candidates.iter().filter(|candidate|{
-                let mut current = searched[sequence_len - 1];
-                let mut current_values = vec![current];
-                let mut next = Some(candidate.1);
-                let mut cand_depth = 1;
-                while next.is_some(){
-                    (current, next) = self[next.unwrap()];
-                    if current != searched[sequence_len - 1 - cand_depth]{
-                        return false;
-                    }
-                    current_values.insert(0, current);
-                    if current_values == searched{
-                        return next == None;
-                    }
-                    if cand_depth >= sequence_len{
-                        return false;
-                    }
-                    cand_depth += 1;
+            let mut code = T::from(*first);
+            for &byte in rest{
+                if let Some(child) = self.find_child(code, byte){
+                    code = child;
+                    continue;
                 }
-                false
-            }).map(|c| c.0).collect();
-        if candidates.len() == 1{
-            match TryInto::<T>::try_into(candidates[0]){
-                Ok(result) => {
-                    return Some(result);
-                },
-                Err(_) => {
-                    println!("candidate is: {:?}", candidates[0]);
-                },
+                if self.is_full(){
+                    break 'phrases;
+                }
+                self.push(&(byte, code));
+                code = T::try_from(self.len() - 1).unwrap();
             }
-            return Some(TryInto::<T>::try_into(candidates[0]).unwrap());
-        }
-        else if candidates.len() > 1{
-            panic!("search should not have several candidates");
-        }
         }
-        None
+        self.preset_id = Some(preset.id());
+        self.preset = Some(PresetWords{
+            words: self.words.clone(),
+            children: self.children.clone(),
+            usage: self.usage.clone(),
+            leaves: self.leaves.clone(),
+        });
     }
 }
 
@@ -97,56 +232,38 @@ impl<T: From<u8> + PartialOrd + Copy + Sub<T, Output = T> + TryInto<usize, Error
 impl<T> Dictionary<T>{
     pub fn get<G>(&self, index: G) -> Option<&(u8, Option<T>)>
     where G: From<u8> + PartialOrd + Copy + Sub<G, Output = G> + TryInto<usize, Error: std::fmt::Debug>{
-        if index <= G::from(u8::MAX){
-            match index.try_into(){
-                Ok(u) => return self.alphabet.get(u),
-                Err(_) => return None
-            }
-        }
-        match (index - G::from(u8::MAX) - G::from(1)).try_into(){
-            Ok(u) => return self.words.get(u),
-            Err(_) => return None
+        let index = index.try_into().ok()?;
+        if index < self.alphabet.len(){
+            return self.alphabet.get(index);
         }
+        self.words.get(index.checked_sub(self.alphabet.len() + self.reserved)?)
     }
 }
 impl<T: From<u8> + PartialOrd + Copy + Sub<T, Output = T> + TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + std::fmt::Debug> Dictionary<T>{
     pub fn get_phrase<G>(&self, index: G) -> Option<Vec<u8>>
     where G: From<u8> + PartialOrd + Copy + Sub<G, Output = G> + TryInto<usize, Error: std::fmt::Debug>{
-        if index <= G::from(u8::MAX){
-            match index.try_into(){
-                Ok(u) => return Some(vec![self.alphabet.get(u)?.0]),
-                Err(_) => return None
+        let phrase_end = self.get(index)?;
+        let mut output = VecDeque::new();
+        output.push_front(phrase_end.0);
+        let mut other_index = phrase_end.1;
+        while let Some(index) = other_index{
+            match self.get(index){
+                Some(subphrase) => {
+                    output.push_front(subphrase.0);
+                    //output.push(subphrase.0);
+                    other_index = subphrase.1;
+                },
+                None => break,
             }
         }
-        match (index - G::from(u8::MAX) - G::from(1)).try_into(){
-            Ok(u) => {
-                let phrase_end = self.words.get(u)?;
-                let mut output = VecDeque::new();
-                output.push_front(phrase_end.0);
-                let mut other_index = phrase_end.1;
-                while let Some(index) = other_index{
-                    match self.get(index){
-                        Some(subphrase) => {
-                            output.push_front(subphrase.0);
-                            //output.push(subphrase.0);
-                            other_index = subphrase.1;
-                        },
-                        None => break,
-                    }
-                }
-                Some(output.into())
-            },
-            Err(_) => return None
-        }
+        Some(output.into())
     }
 }
 
 
 impl<T: std::fmt::Debug> Default for Dictionary<T>{
     fn default() -> Self {
-        let alphabet: Vec<_> = (0..=u8::MAX).map(|byte| (byte, None)).collect();
-        let alphabet = alphabet.try_into().unwrap();
-        Self { alphabet, words: vec![], filled: FilledBehaviour::Clear }
+        Self::with_alphabet(256)
     }
 }
 
@@ -156,11 +273,11 @@ impl<T, G: From<u8> + PartialOrd + Copy + Sub<G, Output = G> + TryInto<usize, Er
     type Output = (u8, Option<T>);
 
     fn index(&self, index: G) -> &Self::Output {
-        if index <= G::from(u8::MAX){
-            TryInto::<usize>::try_into(index).unwrap();
-            return &self.alphabet[index.try_into().unwrap()]
+        let index: usize = index.try_into().unwrap();
+        if index < self.alphabet.len(){
+            &self.alphabet[index]
         }else{
-            &self.words[(index - G::from(u8::MAX)).try_into().unwrap()  - 1]
+            &self.words[index - self.alphabet.len() - self.reserved]
         }
     }
 }
@@ -168,11 +285,11 @@ impl<T, G: From<u8> + PartialOrd + Copy + Sub<G, Output = G> + TryInto<usize, Er
 impl<T, G: From<u8> + PartialOrd + Copy + Sub<G, Output = G> + TryInto<usize, Error: std::fmt::Debug>> IndexMut<G> for Dictionary<T>
 {
     fn index_mut(&mut self, index: G) -> &mut Self::Output {
-        if index < G::from(u8::MAX){
-            TryInto::<usize>::try_into(index).unwrap();
-            return &mut self.alphabet[index.try_into().unwrap()]
+        let index: usize = index.try_into().unwrap();
+        if index < self.alphabet.len(){
+            &mut self.alphabet[index]
         }else{
-            &mut self.words[(index - G::from(u8::MAX - 1)).try_into().unwrap()]
+            &mut self.words[index - self.alphabet.len() - self.reserved]
         }
     }
 }
\ No newline at end of file
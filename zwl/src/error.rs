@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum ZwlError{
+    BadHeader(String),
+    UnsupportedWidth(u8),
+    InvalidCode(usize),
+    UnexpectedEof,
+    ChecksumMismatch{ expected: u32, actual: u32 },
+    PresetMismatch{ expected: u32, actual: Option<u32> },
+    Io(std::io::Error),
+}
+
+impl Display for ZwlError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            ZwlError::BadHeader(reason) => write!(f, "bad header: {reason}"),
+            ZwlError::UnsupportedWidth(width) => write!(f, "unsupported index width: {width} bits"),
+            ZwlError::InvalidCode(code) => write!(f, "invalid code in stream: {code}"),
+            ZwlError::UnexpectedEof => write!(f, "unexpected end of stream"),
+            ZwlError::ChecksumMismatch { expected, actual } => write!(f, "checksum mismatch: expected {expected:#010x}, got {actual:#010x}"),
+            ZwlError::PresetMismatch { expected, actual: Some(actual) } => write!(f, "stream needs preset dictionary {expected:#010x}, got {actual:#010x}"),
+            ZwlError::PresetMismatch { expected, actual: None } => write!(f, "stream needs preset dictionary {expected:#010x}"),
+            ZwlError::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ZwlError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self{
+            ZwlError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ZwlError{
+    fn from(value: std::io::Error) -> Self {
+        match value.kind(){
+            std::io::ErrorKind::UnexpectedEof => Self::UnexpectedEof,
+            _ => Self::Io(value),
+        }
+    }
+}
+
+impl From<ZwlError> for std::io::Error{
+    fn from(value: ZwlError) -> Self {
+        match value{
+            ZwlError::Io(error) => error,
+            ZwlError::UnexpectedEof => std::io::Error::new(std::io::ErrorKind::UnexpectedEof, value),
+            _ => std::io::Error::new(std::io::ErrorKind::InvalidData, value),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::io::{Read, Write};
+
+use crate::{bit_io::{BitReader, BitWriter}, code_width::LikeU16, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError};
+
+pub const GIF_MIN_CODE_SIZE: u8 = 2;
+pub const GIF_MAX_CODE_SIZE: u8 = 8;
+pub const GIF_MAX_BITS: usize = 12;
+const SUB_BLOCK_LEN: usize = 255;
+
+fn new_dictionary(min_code_size: u8) -> Dictionary<LikeU16>{
+    let mut dictionary = Dictionary::with_alphabet(1 << min_code_size);
+    dictionary.filled = FilledBehaviour::Freeze;
+    dictionary.reserved = 2;
+    dictionary
+}
+
+fn check_code_size(min_code_size: u8) -> Result<(), ZwlError>{
+    if !(GIF_MIN_CODE_SIZE..=GIF_MAX_CODE_SIZE).contains(&min_code_size){
+        return Err(ZwlError::UnsupportedWidth(min_code_size));
+    }
+    Ok(())
+}
+
+// Splits the written bytes into sub-blocks of at most 255 bytes, each prefixed by its length
+pub struct SubBlockWriter<W: Write>{
+    pub output: W,
+    block: Vec<u8>,
+}
+
+impl<W: Write> SubBlockWriter<W>{
+    pub fn new(output: W) -> Self{
+        Self { output, block: Vec::with_capacity(SUB_BLOCK_LEN) }
+    }
+    fn write_block(&mut self) -> std::io::Result<()>{
+        if !self.block.is_empty(){
+            self.output.write_all(&[self.block.len() as u8])?;
+            self.output.write_all(&self.block)?;
+            self.block.clear();
+        }
+        Ok(())
+    }
+    // Writes the last sub-block and the zero length block terminator
+    pub fn finish(&mut self) -> std::io::Result<()>{
+        self.write_block()?;
+        self.output.write_all(&[0])?;
+        self.output.flush()
+    }
+}
+
+impl<W: Write> Write for SubBlockWriter<W>{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let count = buf.len().min(SUB_BLOCK_LEN - self.block.len());
+        self.block.extend_from_slice(&buf[..count]);
+        if self.block.len() == SUB_BLOCK_LEN{
+            self.write_block()?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// Joins sub-blocks back together and stops at the block terminator
+pub struct SubBlockReader<R: Read>{
+    pub input: R,
+    remaining: usize,
+    finished: bool,
+}
+
+impl<R: Read> SubBlockReader<R>{
+    pub fn new(input: R) -> Self{
+        Self { input, remaining: 0, finished: false }
+    }
+}
+
+impl<R: Read> Read for SubBlockReader<R>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.remaining == 0{
+            if self.finished{
+                return Ok(0);
+            }
+            let mut len = [0];
+            self.input.read_exact(&mut len)?;
+            self.remaining = len[0].into();
+            self.finished = self.remaining == 0;
+        }
+        let count = buf.len().min(self.remaining);
+        let count = self.input.read(&mut buf[..count])?;
+        if count == 0{
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= count;
+        Ok(count)
+    }
+}
+
+pub struct GifEncoder<I: Read>{
+    input: I,
+    pub min_code_size: u8,
+}
+
+impl<I: Read> GifEncoder<I>{
+    pub fn new(input: I, min_code_size: u8) -> Self{
+        Self { input, min_code_size }
+    }
+    // Writes the LZW minimum code size followed by the image data sub-blocks
+    pub fn encode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        check_code_size(self.min_code_size)?;
+        output.write_all(&[self.min_code_size])?;
+        let mut blocks = SubBlockWriter::new(&mut output);
+        {
+            let mut state = GifEncoderState{
+                writtable: BitWriter::new(&mut blocks),
+                dictionary: new_dictionary(self.min_code_size),
+                bits: self.min_code_size as usize + 1,
+                min_code_size: self.min_code_size,
+            };
+            let clear_code = state.dictionary.reserved_code(0) as u64;
+            let end_code = state.dictionary.reserved_code(1) as u64;
+            state.write_code(clear_code)?;
+            let mut index: Option<LikeU16> = None;
+            let mut buf = [0; 4096];
+            let mut s = self.input.read(&mut buf)?;
+            while s > 0{
+                for &byte in &buf[..s]{
+                    if usize::from(byte) >= state.dictionary.alphabet.len(){
+                        return Err(ZwlError::InvalidCode(byte.into()));
+                    }
+                    let Some(prefix) = index else {
+                        index = Some(LikeU16::from(byte));
+                        continue;
+                    };
+                    if let Some(found) = state.dictionary.find_child(prefix, byte){
+                        index = Some(found);
+                        continue;
+                    }
+                    state.write_code(prefix.0)?;
+                    if state.dictionary.len() < 1 << GIF_MAX_BITS{
+                        state.dictionary.push(&(byte, prefix));
+                    }
+                    else{
+                        state.write_code(clear_code)?;
+                    }
+                    index = Some(LikeU16::from(byte));
+                }
+                s = self.input.read(&mut buf)?;
+            }
+            if let Some(last) = index{
+                state.write_code(last.0)?;
+            }
+            state.write_code(end_code)?;
+            state.writtable.finish()?;
+        }
+        blocks.finish()?;
+        Ok(())
+    }
+}
+
+struct GifEncoderState<W: Write>{
+    writtable: BitWriter<W>,
+    dictionary: Dictionary<LikeU16>,
+    bits: usize,
+    min_code_size: u8,
+}
+
+impl<W: Write> GifEncoderState<W>{
+    fn write_code(&mut self, code: u64) -> std::io::Result<()>{
+        self.writtable.write_bits(code, self.bits as u32)?;
+        if code == self.dictionary.reserved_code(0) as u64{
+            self.dictionary.clear();
+            self.bits = self.min_code_size as usize + 1;
+        }
+        else if self.dictionary.len() >= 1 << self.bits && self.bits < GIF_MAX_BITS{
+            self.bits += 1;
+        }
+        Ok(())
+    }
+}
+
+pub struct GifDecoder<I: Read>{
+    input: BitReader<SubBlockReader<I>>,
+    pub min_code_size: u8,
+    dictionary: Dictionary<LikeU16>,
+    bits: usize,
+    old_index: Option<LikeU16>,
+    old_sequence: Vec<u8>,
+    finished: bool,
+}
+
+impl<I: Read> GifDecoder<I>{
+    // Reads the LZW minimum code size, the sub-blocks follow it
+    pub fn new(mut input: I) -> Result<Self, ZwlError>{
+        let mut min_code_size = [0];
+        input.read_exact(&mut min_code_size)?;
+        let [min_code_size] = min_code_size;
+        check_code_size(min_code_size)?;
+        Ok(Self{
+            input: BitReader::new(SubBlockReader::new(input)),
+            min_code_size,
+            dictionary: new_dictionary(min_code_size),
+            bits: min_code_size as usize + 1,
+            old_index: None,
+            old_sequence: vec![],
+            finished: false,
+        })
+    }
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        while let Some(sequence) = self.next_sequence()?{
+            output.write_all(sequence)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        let clear_code = self.dictionary.reserved_code(0);
+        let end_code = self.dictionary.reserved_code(1);
+        loop{
+            if self.finished{
+                return Ok(None);
+            }
+            let code = match self.input.read_bits(self.bits as u32){
+                Ok(code) => code as usize,
+                // Some encoders leave out the end code
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.finished = true;
+                    return Ok(None);
+                },
+                Err(e) => return Err(e.into()),
+            };
+            if code == clear_code{
+                self.dictionary.clear();
+                self.bits = self.min_code_size as usize + 1;
+                self.old_index = None;
+                continue;
+            }
+            if code == end_code{
+                self.finished = true;
+                // Skip whatever is left up to the block terminator
+                std::io::copy(&mut self.input.input, &mut std::io::sink())?;
+                return Ok(None);
+            }
+            let index = LikeU16::try_from(code).map_err(|_| ZwlError::InvalidCode(code))?;
+            let Some(old_index) = self.old_index else {
+                if code >= self.dictionary.alphabet.len(){
+                    return Err(ZwlError::InvalidCode(code));
+                }
+                self.old_index = Some(index);
+                self.old_sequence = vec![code as u8];
+                return Ok(Some(&self.old_sequence));
+            };
+            let sequence = if code == self.dictionary.len() && code < 1 << GIF_MAX_BITS{
+                let mut sequence = self.old_sequence.clone();
+                sequence.push(self.old_sequence[0]);
+                sequence
+            }
+            else{
+                self.dictionary.get_phrase(index).ok_or(ZwlError::InvalidCode(code))?
+            };
+            if self.dictionary.len() < 1 << GIF_MAX_BITS{
+                self.dictionary.push(&(sequence[0], old_index));
+                if self.dictionary.len() >= 1 << self.bits && self.bits < GIF_MAX_BITS{
+                    self.bits += 1;
+                }
+            }
+            self.old_index = Some(index);
+            self.old_sequence = sequence;
+            return Ok(Some(&self.old_sequence));
+        }
+    }
+}
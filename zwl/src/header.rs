@@ -0,0 +1,202 @@
+use std::io::{Read, Write};
+
+use crate::{dictionary::FilledBehaviour, error::ZwlError};
+
+pub const MAGIC: [u8; 4] = *b"ZWL\x1a";
+pub const LEGACY_VERSION: u8 = 0;
+pub const CURRENT_VERSION: u8 = 1;
+
+pub const FLAG_ORIGINAL_LEN: u16 = 1;
+pub const FLAG_FILE_NAME: u16 = 1 << 1;
+pub const FLAG_CHECKSUM: u16 = 1 << 2;
+pub const FLAG_END_CODE: u16 = 1 << 3;
+pub const FLAG_CLEAR_CODE: u16 = 1 << 4;
+pub const FLAG_IMPLICIT_WIDTH: u16 = 1 << 5;
+pub const FLAG_PRESET: u16 = 1 << 6;
+pub const FLAG_BLOCKS: u16 = 1 << 7;
+pub const FLAG_SEEKABLE: u16 = 1 << 8;
+const KNOWN_FLAGS: u16 = FLAG_ORIGINAL_LEN | FLAG_FILE_NAME | FLAG_CHECKSUM | FLAG_END_CODE | FLAG_CLEAR_CODE | FLAG_IMPLICIT_WIDTH | FLAG_PRESET | FLAG_BLOCKS | FLAG_SEEKABLE;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZwlHeader{
+    pub version: u8,
+    pub bit_size: u8,
+    pub filled: FilledBehaviour,
+    pub original_len: Option<u64>,
+    pub file_name: Option<String>,
+    pub checksum: bool,
+    pub end_code: bool,
+    pub clear_code: bool,
+    pub implicit_width: bool,
+    pub preset_id: Option<u32>,
+    // Input bytes per independently compressed block
+    pub block_size: Option<u32>,
+    // Blocks are followed by an index of their offsets
+    pub seekable: bool,
+}
+
+impl ZwlHeader{
+    pub fn new(bit_size: u8, filled: FilledBehaviour) -> Self{
+        Self{
+            version: CURRENT_VERSION,
+            bit_size,
+            filled,
+            original_len: None,
+            file_name: None,
+            checksum: false,
+            end_code: false,
+            clear_code: false,
+            implicit_width: false,
+            preset_id: None,
+            block_size: None,
+            seekable: false,
+        }
+    }
+    pub fn legacy(bit_size: u8, filled: FilledBehaviour) -> Self{
+        Self{
+            version: LEGACY_VERSION,
+            ..Self::new(bit_size, filled)
+        }
+    }
+    pub fn flags(&self) -> u16{
+        let mut flags = 0;
+        if self.original_len.is_some(){
+            flags |= FLAG_ORIGINAL_LEN;
+        }
+        if self.file_name.is_some(){
+            flags |= FLAG_FILE_NAME;
+        }
+        if self.checksum{
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.end_code{
+            flags |= FLAG_END_CODE;
+        }
+        if self.clear_code{
+            flags |= FLAG_CLEAR_CODE;
+        }
+        if self.implicit_width{
+            flags |= FLAG_IMPLICIT_WIDTH;
+        }
+        if self.preset_id.is_some(){
+            flags |= FLAG_PRESET;
+        }
+        if self.block_size.is_some(){
+            flags |= FLAG_BLOCKS;
+        }
+        if self.seekable{
+            flags |= FLAG_SEEKABLE;
+        }
+        flags
+    }
+    pub fn write_to<O: Write>(&self, output: &mut O) -> std::io::Result<()>{
+        if self.version == LEGACY_VERSION{
+            return output.write_all(&[self.bit_size, filled_to_byte(&self.filled)]);
+        }
+        output.write_all(&MAGIC)?;
+        output.write_all(&[self.version, self.bit_size, filled_to_byte(&self.filled)])?;
+        output.write_all(&self.flags().to_le_bytes())?;
+        if let Some(original_len) = self.original_len{
+            output.write_all(&original_len.to_le_bytes())?;
+        }
+        if let Some(file_name) = &self.file_name{
+            let name_len = u16::try_from(file_name.len()).map_err(|_| std::io::Error::other("File name stored in the header is too long"))?;
+            output.write_all(&name_len.to_le_bytes())?;
+            output.write_all(file_name.as_bytes())?;
+        }
+        if let Some(preset_id) = self.preset_id{
+            output.write_all(&preset_id.to_le_bytes())?;
+        }
+        if let Some(block_size) = self.block_size{
+            output.write_all(&block_size.to_le_bytes())?;
+        }
+        Ok(())
+    }
+    // Combinations of reserved codes that decoders support. Implicit width growth needs the
+    // clear code, the dictionary clearing itself when full would desync the width otherwise
+    pub fn check_codes(&self) -> Result<(), ZwlError>{
+        if self.clear_code && !self.end_code{
+            return Err(ZwlError::BadHeader("Clear code is only supported together with the end code".into()));
+        }
+        if self.implicit_width && !self.clear_code{
+            return Err(ZwlError::BadHeader("Implicit width growth is only supported together with the clear code".into()));
+        }
+        Ok(())
+    }
+    pub fn read_from<I: Read>(input: &mut I) -> Result<Self, ZwlError>{
+        let mut first = [0];
+        input.read_exact(&mut first)?;
+        if first[0] != MAGIC[0]{
+            let mut filled = [0];
+            input.read_exact(&mut filled)?;
+            return Ok(Self::legacy(first[0], filled_from_byte(filled[0])?));
+        }
+        let mut magic_rest = [0; 3];
+        input.read_exact(&mut magic_rest)?;
+        if magic_rest != MAGIC[1..]{
+            return Err(ZwlError::BadHeader("Magic number does not match".into()));
+        }
+        let mut fixed = [0; 5];
+        input.read_exact(&mut fixed)?;
+        let [version, bit_size, filled, flags_low, flags_high] = fixed;
+        if version != CURRENT_VERSION{
+            return Err(ZwlError::BadHeader(format!("Unsupported format version {version}")));
+        }
+        let flags = u16::from_le_bytes([flags_low, flags_high]);
+        if flags & !KNOWN_FLAGS != 0{
+            return Err(ZwlError::BadHeader(format!("Unknown header flags {flags:#06x}")));
+        }
+        let mut header = Self::new(bit_size, filled_from_byte(filled)?);
+        header.checksum = flags & FLAG_CHECKSUM != 0;
+        header.end_code = flags & FLAG_END_CODE != 0;
+        header.clear_code = flags & FLAG_CLEAR_CODE != 0;
+        header.implicit_width = flags & FLAG_IMPLICIT_WIDTH != 0;
+        header.seekable = flags & FLAG_SEEKABLE != 0;
+        if header.seekable && flags & FLAG_BLOCKS == 0{
+            return Err(ZwlError::BadHeader("Block index is only supported in block mode".into()));
+        }
+        header.check_codes()?;
+        if flags & FLAG_ORIGINAL_LEN != 0{
+            let mut original_len = [0; 8];
+            input.read_exact(&mut original_len)?;
+            header.original_len = Some(u64::from_le_bytes(original_len));
+        }
+        if flags & FLAG_FILE_NAME != 0{
+            let mut name_len = [0; 2];
+            input.read_exact(&mut name_len)?;
+            let mut file_name = vec![0; u16::from_le_bytes(name_len).into()];
+            input.read_exact(&mut file_name)?;
+            header.file_name = Some(String::from_utf8(file_name).map_err(|_| ZwlError::BadHeader("File name is not valid UTF-8".into()))?);
+        }
+        if flags & FLAG_PRESET != 0{
+            let mut preset_id = [0; 4];
+            input.read_exact(&mut preset_id)?;
+            header.preset_id = Some(u32::from_le_bytes(preset_id));
+        }
+        if flags & FLAG_BLOCKS != 0{
+            let mut block_size = [0; 4];
+            input.read_exact(&mut block_size)?;
+            header.block_size = Some(u32::from_le_bytes(block_size));
+        }
+        Ok(header)
+    }
+}
+
+fn filled_to_byte(filled: &FilledBehaviour) -> u8{
+    match filled{
+        FilledBehaviour::Clear => 0,
+        FilledBehaviour::Freeze => 1,
+        FilledBehaviour::Adaptive => 2,
+        FilledBehaviour::Lru => 3,
+    }
+}
+
+fn filled_from_byte(byte: u8) -> Result<FilledBehaviour, ZwlError>{
+    match byte{
+        0 => Ok(FilledBehaviour::Clear),
+        1 => Ok(FilledBehaviour::Freeze),
+        2 => Ok(FilledBehaviour::Adaptive),
+        3 => Ok(FilledBehaviour::Lru),
+        _ => Err(ZwlError::BadHeader("Header does not say if dictionary should clear or freeze when it is full".into()))
+    }
+}
@@ -1,17 +1,35 @@
-pub mod like_u12;
-pub mod like_u16;
-pub mod like_u32;
-pub mod like_u64;
+pub mod code_width;
+pub mod bit_io;
 pub mod bit_encoder;
 pub mod bit_decoder;
 pub mod traits;
 pub mod dictionary;
+pub mod error;
+pub mod checksum;
+pub mod header;
+pub mod auto_decoder;
+pub mod stream;
+pub mod unix_compress;
+pub mod gif;
+pub mod tiff_lzw;
+pub mod preset;
+pub mod block;
+pub mod seekable;
+pub mod members;
+pub mod archive;
+pub mod stats;
+
+pub use auto_decoder::{decode_auto, get_decoder, get_decoder_and_header, get_decoder_with_options, DecodeOptions, ZwlDecoderE};
+pub use stream::{ZwlReader, ZwlWriter};
+pub use seekable::ZwlSeekableReader;
+pub use members::ZwlMemberDecoder;
+pub use archive::{ZwlArchiveReader, ZwlArchiveWriter};
 #[cfg(test)]
 mod tests {
-    use crate::{bit_decoder::ZwlBitDecoder, bit_encoder::ZwlBitEncoder, like_u12::LikeU12, like_u16::LikeU16, like_u32::LikeU32, like_u64::LikeU64, dictionary::FilledBehaviour};
+    use crate::{bit_decoder::ZwlBitDecoder, bit_encoder::ZwlBitEncoder, code_width::{CodeWidth, LikeU12, LikeU16, LikeU32, LikeU64}, dictionary::FilledBehaviour, error::ZwlError, header::ZwlHeader, unix_compress::{ZDecoder, ZEncoder, Z_MAX_BITS}, gif::{GifDecoder, GifEncoder}, tiff_lzw::{TiffLzwDecoder, TiffLzwEncoder}, preset::PresetDictionary, auto_decoder::{get_decoder_with_options, DecodeOptions}, archive::{ArchiveEntry, EntryKind}};
 
     use super::*;
-    use std::io;
+    use std::io::{self, Read, Seek, Write};
     
     const PREAMBLE: &str =  "The Project Gutenberg eBook of The Ethics of Aristotle
     
@@ -22,17 +40,34 @@ of the Project Gutenberg License included with this ebook or online
 at www.gutenberg.org. If you are not located in the United States,
 you will have to check the laws of the country where you are located
 before using this eBook.";
+
+    fn noisy_text(len: usize, alphabet: &[u8]) -> Vec<u8>{
+        let mut state: u32 = 0x2545_F491;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            alphabet[(state >> 16) as usize % alphabet.len()]
+        }).collect()
+    }
+
+    fn payload(buffer: &[u8]) -> &[u8]{
+        let mut rest = buffer;
+        let header = ZwlHeader::read_from(&mut rest).unwrap();
+        if header.checksum{
+            return &rest[..rest.len() - checksum::CHECKSUM_LEN];
+        }
+        rest
+    }
     #[test]
     fn encoding_decoding_l_u12() {
         let cursor = io::Cursor::new(PREAMBLE.as_bytes());
         let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(cursor, FilledBehaviour::Clear);
         
         
-        let mut buffer = vec![0u8; PREAMBLE.len() * 4];
+        let mut buffer = vec![];
         let mut buffer_d = vec![0u8; PREAMBLE.len()];
-        assert!(encoder.encode(&mut buffer[..]).is_ok());
+        assert!(encoder.encode(&mut buffer).is_ok());
         println!("{:?}", buffer);
-        let mut decoder = ZwlBitDecoder::<LikeU12, _>::new(&buffer[2..], FilledBehaviour::Clear);
+        let mut decoder = ZwlBitDecoder::<LikeU12, _>::new(payload(&buffer), FilledBehaviour::Clear);
         assert!(decoder.decode(&mut buffer_d[..]).is_ok());
 
         println!("-----");
@@ -81,11 +116,11 @@ before using this eBook.";
     fn longer_text(){
         let cursor = io::Cursor::new(PREAMBLE.as_bytes());
         let mut encoder: ZwlBitEncoder<LikeU16, io::Cursor<&[u8]>> = ZwlBitEncoder::<LikeU16, io::Cursor<&[u8]>>::new(cursor, FilledBehaviour::Clear);
-        let mut buffer = vec![0u8; PREAMBLE.len() * 4];
+        let mut buffer = vec![];
         let mut buffer_d = vec![0u8; PREAMBLE.len()];
-        assert!(encoder.encode(&mut buffer[..]).is_ok());
+        assert!(encoder.encode(&mut buffer).is_ok());
         println!("{:?}", buffer);
-        let mut decoder = ZwlBitDecoder::<LikeU16, _>::new(&buffer[2..], FilledBehaviour::Clear);
+        let mut decoder = ZwlBitDecoder::<LikeU16, _>::new(payload(&buffer), FilledBehaviour::Clear);
         assert!(decoder.decode(&mut buffer_d[..]).is_ok());
 
         assert_eq!(Ok(PREAMBLE), str::from_utf8(&buffer_d.to_vec()))
@@ -97,11 +132,11 @@ before using this eBook.";
         let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(cursor, FilledBehaviour::Clear);
         
         
-        let mut buffer = vec![0u8; PREAMBLE.len() * 4];
+        let mut buffer = vec![];
         let mut buffer_d = vec![0u8; PREAMBLE.len()];
-        assert!(encoder.encode(&mut buffer[..]).is_ok());
+        assert!(encoder.encode(&mut buffer).is_ok());
         println!("{:?}", buffer);
-        let mut decoder = ZwlBitDecoder::<LikeU16, _>::new(&buffer[2..], FilledBehaviour::Clear);
+        let mut decoder = ZwlBitDecoder::<LikeU16, _>::new(payload(&buffer), FilledBehaviour::Clear);
         assert!(decoder.decode(&mut buffer_d[..]).is_ok());
 
         println!("-----");
@@ -121,11 +156,11 @@ before using this eBook.";
         let mut encoder = ZwlBitEncoder::<LikeU32, _>::new(cursor, FilledBehaviour::Clear);
         
         
-        let mut buffer = vec![0u8; PREAMBLE.len() * 4];
+        let mut buffer = vec![];
         let mut buffer_d = vec![0u8; PREAMBLE.len()];
-        assert!(encoder.encode(&mut buffer[..]).is_ok());
+        assert!(encoder.encode(&mut buffer).is_ok());
         println!("{:?}", buffer);
-        let mut decoder = ZwlBitDecoder::<LikeU32, _>::new(&buffer[2..], FilledBehaviour::Clear);
+        let mut decoder = ZwlBitDecoder::<LikeU32, _>::new(payload(&buffer), FilledBehaviour::Clear);
         assert!(decoder.decode(&mut buffer_d[..]).is_ok());
 
         println!("-----");
@@ -145,11 +180,11 @@ before using this eBook.";
         let mut encoder = ZwlBitEncoder::<LikeU64, _>::new(cursor, FilledBehaviour::Clear);
         
         
-        let mut buffer = vec![0u8; PREAMBLE.len() * 4];
+        let mut buffer = vec![];
         let mut buffer_d = vec![0u8; PREAMBLE.len()];
-        assert!(encoder.encode(&mut buffer[..]).is_ok());
+        assert!(encoder.encode(&mut buffer).is_ok());
         println!("{:?}", buffer);
-        let mut decoder = ZwlBitDecoder::<LikeU64, _>::new(&buffer[2..], FilledBehaviour::Clear);
+        let mut decoder = ZwlBitDecoder::<LikeU64, _>::new(payload(&buffer), FilledBehaviour::Clear);
         assert!(decoder.decode(&mut buffer_d[..]).is_ok());
 
         println!("-----");
@@ -163,4 +198,702 @@ before using this eBook.";
 
         assert_eq!(str::from_utf8(&buffer_d.to_vec()), Ok(PREAMBLE))
     }
+
+    #[test]
+    fn dictionary_find_matches_phrases(){
+        let cursor = io::Cursor::new(PREAMBLE.as_bytes());
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(cursor, FilledBehaviour::Clear);
+        assert!(encoder.encode_headerless(io::sink()).is_ok());
+        for code in encoder.dictionary.reserved_code(encoder.dictionary.reserved)..encoder.dictionary.len(){
+            let phrase = encoder.dictionary.get_phrase(LikeU12::try_from(code).unwrap()).unwrap();
+            assert_eq!(encoder.dictionary.find(&phrase), Some(LikeU12::try_from(code).unwrap()));
+        }
+    }
+
+    #[test]
+    fn corrupt_stream_is_an_error(){
+        let mut decoder = ZwlBitDecoder::<LikeU12, _>::new(&[0xFF, 0xFF][..], FilledBehaviour::Clear);
+        assert!(matches!(decoder.decode(io::sink()), Err(ZwlError::InvalidCode(511))));
+
+        let mut buffer = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear);
+        assert!(encoder.encode_headerless(&mut buffer).is_ok());
+        for i in 0..buffer.len(){
+            let mut corrupted = buffer.clone();
+            corrupted[i] ^= 0xA5;
+            let mut decoder = ZwlBitDecoder::<LikeU12, _>::new(&corrupted[..], FilledBehaviour::Clear);
+            let _ = decoder.decode(io::sink());
+        }
+    }
+
+    #[test]
+    fn decode_auto_detects_width(){
+        let mut encoded = vec![vec![], vec![], vec![], vec![]];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut encoded[0]).is_ok());
+        assert!(ZwlBitEncoder::<LikeU16, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Freeze).encode(&mut encoded[1]).is_ok());
+        assert!(ZwlBitEncoder::<LikeU32, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut encoded[2]).is_ok());
+        assert!(ZwlBitEncoder::<LikeU64, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Freeze).encode(&mut encoded[3]).is_ok());
+        for buffer in encoded{
+            let mut buffer_d = vec![];
+            assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+            assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+        }
+        assert!(matches!(get_decoder(&[8u8, 0][..]), Err(ZwlError::UnsupportedWidth(8))));
+        assert!(matches!(get_decoder(&[65u8, 0][..]), Err(ZwlError::UnsupportedWidth(65))));
+    }
+
+    #[test]
+    fn arbitrary_code_widths(){
+        let text = noisy_text(50_000, b"abcdefghij klmnop\n");
+        let mut encoded = [vec![], vec![], vec![]];
+        assert!(ZwlBitEncoder::<CodeWidth<9>, _>::new(&text[..], FilledBehaviour::Clear).encode(&mut encoded[0]).is_ok());
+        assert!(ZwlBitEncoder::<CodeWidth<14>, _>::new(&text[..], FilledBehaviour::Freeze).encode(&mut encoded[1]).is_ok());
+        assert!(ZwlBitEncoder::<CodeWidth<20>, _>::new(&text[..], FilledBehaviour::Clear).encode(&mut encoded[2]).is_ok());
+        for (buffer, bit_size) in encoded.iter().zip([9, 14, 20]){
+            assert_eq!(ZwlHeader::read_from(&mut &buffer[..]).unwrap().bit_size, bit_size);
+            let mut buffer_d = vec![];
+            assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+            assert_eq!(text, buffer_d);
+        }
+    }
+
+    #[test]
+    fn streaming_writer_and_reader(){
+        let mut buffer = vec![];
+        let mut writer = ZwlWriter::<LikeU12, _>::new(&mut buffer, FilledBehaviour::Clear).unwrap();
+        for chunk in PREAMBLE.as_bytes().chunks(7){
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut encoded = vec![];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut encoded).is_ok());
+        assert_eq!(encoded, buffer);
+
+        let mut reader = ZwlReader::new(&buffer[..]).unwrap();
+        let mut small = [0u8; 5];
+        let mut buffer_d = vec![];
+        loop{
+            let count = reader.read(&mut small).unwrap();
+            if count == 0{
+                break;
+            }
+            buffer_d.extend_from_slice(&small[..count]);
+        }
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+
+        let mut copied = vec![];
+        {
+            let mut writer = ZwlWriter::<LikeU16, _>::new(&mut copied, FilledBehaviour::Freeze).unwrap();
+            io::copy(&mut PREAMBLE.as_bytes(), &mut writer).unwrap();
+        }
+        let mut buffer_d = vec![];
+        io::copy(&mut ZwlReader::new(&copied[..]).unwrap(), &mut buffer_d).unwrap();
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+    }
+
+    #[test]
+    fn versioned_and_legacy_headers(){
+        let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Freeze);
+        encoder.original_len = Some(PREAMBLE.len() as u64);
+        encoder.file_name = Some("preamble.txt".into());
+        let mut buffer = vec![];
+        assert!(encoder.encode(&mut buffer).is_ok());
+        assert_eq!(&buffer[..4], &header::MAGIC);
+        let mut buffer_d = vec![];
+        let (read_header, mut decoder) = get_decoder_and_header(&buffer[..]).unwrap();
+        assert_eq!(read_header, encoder.header());
+        assert!(decoder.decode(&mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+
+        let mut legacy = vec![];
+        ZwlHeader::legacy(12, FilledBehaviour::Clear).write_to(&mut legacy).unwrap();
+        assert_eq!(legacy, vec![12, 0]);
+        assert!(ZwlBitEncoder::<LikeU12, _>::new_legacy(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode_headerless(&mut legacy).is_ok());
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&legacy[..], &mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+
+        assert!(matches!(get_decoder(&b"ZWLX\x01\x0c\x00\x00\x00"[..]), Err(ZwlError::BadHeader(_))));
+        assert!(matches!(get_decoder(&b"ZWL\x1a\x01\x0c\x00\x00\x80"[..]), Err(ZwlError::BadHeader(_))));
+        assert!(matches!(get_decoder(&b"ZWL\x1a\x01"[..]), Err(ZwlError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn checksum_detects_corruption(){
+        let mut crc = checksum::Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.value(), 0xCBF4_3926);
+
+        let mut buffer = vec![];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut buffer).is_ok());
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0x01;
+        assert!(matches!(decode_auto(&buffer[..], io::sink()), Err(ZwlError::ChecksumMismatch { .. })));
+        let options = DecodeOptions { verify_checksum: false, ..Default::default() };
+        let (_, mut decoder) = get_decoder_with_options(&buffer[..], &options).unwrap();
+        let mut buffer_d = vec![];
+        assert!(decoder.decode(&mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+
+        assert!(decode_auto(&buffer[..buffer.len() - 2], io::sink()).is_err());
+    }
+
+    #[test]
+    fn end_code_stops_before_trailing_data(){
+        let mut buffer = vec![];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut buffer).is_ok());
+        let encoded_len = buffer.len();
+        buffer.extend_from_slice(&[0xFF; 16]);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+        let mut decoder = ZwlMemberDecoder::new(&buffer[..], &DecodeOptions::default()).unwrap();
+        assert!(decoder.decode(io::sink()).is_ok());
+        assert!(decoder.trailing_data);
+
+        let mut empty = vec![];
+        assert!(ZwlBitEncoder::<LikeU16, _>::new(&b""[..], FilledBehaviour::Freeze).encode(&mut empty).is_ok());
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&empty[..], &mut buffer_d).is_ok());
+        assert!(buffer_d.is_empty());
+
+        let mut headerless = vec![];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode_headerless(&mut headerless).is_ok());
+        let mut decoder = ZwlBitDecoder::<LikeU12, _>::new(&headerless[..headerless.len() - 2], FilledBehaviour::Clear);
+        assert!(matches!(decoder.decode(io::sink()), Err(ZwlError::UnexpectedEof)));
+        assert!(decode_auto(&buffer[..encoded_len - 6], io::sink()).is_err());
+    }
+
+    #[test]
+    fn clear_code_resets_dictionary(){
+        let text = noisy_text(60_000, b"abcdefghij klmnop\n");
+        let mut buffer = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&text[..], FilledBehaviour::Clear);
+        assert!(encoder.encode(&mut buffer).is_ok());
+        assert!(encoder.dictionary.len() < 4095);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+
+        let mut buffer = vec![];
+        let mut writer = ZwlWriter::<LikeU16, _>::new(&mut buffer, FilledBehaviour::Freeze).unwrap();
+        for chunk in PREAMBLE.as_bytes().chunks(100){
+            writer.write_all(chunk).unwrap();
+            writer.clear_dictionary().unwrap();
+        }
+        writer.finish().unwrap();
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+    }
+
+    #[test]
+    fn adaptive_clears_when_ratio_drops(){
+        let mut text = noisy_text(100_000, b"aaaabbc");
+        text.extend(noisy_text(100_000, b"0123456789xyz"));
+        let encode = |filled| {
+            let mut buffer = vec![];
+            ZwlBitEncoder::<LikeU12, _>::new(&text[..], filled).encode(&mut buffer).unwrap();
+            buffer
+        };
+        let adaptive = encode(FilledBehaviour::Adaptive);
+        assert!(adaptive.len() < encode(FilledBehaviour::Freeze).len());
+        assert_eq!(ZwlHeader::read_from(&mut &adaptive[..]).unwrap().filled, FilledBehaviour::Adaptive);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&adaptive[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+    }
+
+    #[test]
+    fn lru_replaces_least_recently_used_phrases(){
+        let mut text = vec![];
+        for (part, alphabet) in [&b"temp=21.5;hum=40;"[..], b"temp=22.0;hum=41;rpm=900;", b"rpm=950;volt=12.1;"].iter().enumerate(){
+            text.extend(noisy_text(40_000 + part, alphabet));
+        }
+        let mut buffer = vec![];
+        ZwlBitEncoder::<LikeU12, _>::new(&text[..], FilledBehaviour::Lru).encode(&mut buffer).unwrap();
+        assert_eq!(ZwlHeader::read_from(&mut &buffer[..]).unwrap().filled, FilledBehaviour::Lru);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+
+        let mut buffer = vec![];
+        ZwlBitEncoder::<LikeU12, _>::new_legacy(&text[..], FilledBehaviour::Lru).encode(&mut buffer).unwrap();
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+    }
+
+    #[test]
+    fn bit_io_round_trips_any_width(){
+        let values = [(0b1_0110_0001u64, 9), (u64::MAX, 64), (0, 13), (0x1234_5678_9ABC, 57), (1, 1), (0xFF, 8)];
+        let mut buffer = vec![];
+        {
+            let mut writer = crate::bit_io::BitWriter::new(&mut buffer);
+            for (value, count) in values{
+                writer.write_bits(value, count).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert_eq!(buffer.len(), (9 + 64 + 13 + 57 + 1 + 8usize).div_ceil(8));
+        assert_eq!(buffer[0], 0b0110_0001);
+        let mut reader = crate::bit_io::BitReader::new(&buffer[..]);
+        for (value, count) in values{
+            assert_eq!(reader.read_bits(count).unwrap(), value);
+        }
+        assert_eq!(reader.read_bits(64).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn bit_io_layout_is_fixed(){
+        // Same layout as the Vec<bool> bit writer used before, mixed widths cross byte and word bounds
+        let values = [(0x1FFu64, 9), (0, 1), (0b101, 3), (0xABC, 12), (0x1_2345, 17), (0x1_2345_6789, 33), (0xFEDC_BA98_7654_3210, 64), (0b11, 2)];
+        let lsb_first = [0xFF, 0x95, 0x57, 0x8B, 0x46, 0x26, 0x9E, 0x15, 0x8D, 0x84, 0x90, 0xA1, 0xB2, 0xC3, 0xD4, 0xE5, 0xF6, 0x1F];
+        let msb_first = [0xFF, 0xAD, 0x5E, 0x48, 0xD1, 0x64, 0x68, 0xAC, 0xF1, 0x3F, 0xDB, 0x97, 0x53, 0x0E, 0xCA, 0x86, 0x42, 0x18];
+        let mut buffer = vec![];
+        {
+            let mut writer = crate::bit_io::BitWriter::new(&mut buffer);
+            for (value, count) in values{
+                writer.write_bits(value, count).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert_eq!(buffer, lsb_first);
+        let mut reader = crate::bit_io::BitReader::new(&lsb_first[..]);
+        for (value, count) in values{
+            assert_eq!(reader.read_bits(count).unwrap(), value);
+        }
+        let mut buffer = vec![];
+        {
+            let mut writer = crate::bit_io::MsbBitWriter::new(&mut buffer);
+            for (value, count) in values{
+                writer.write_bits(value, count).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert_eq!(buffer, msb_first);
+        let mut reader = crate::bit_io::MsbBitReader::new(&msb_first[..]);
+        for (value, count) in values{
+            assert_eq!(reader.read_bits(count).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn unix_compress_format(){
+        // Checked with `gzip -dc`
+        let fixture = [0x1f, 0x9d, 0x90, 0x54, 0x9e, 0x08, 0x29, 0xf2, 0x44, 0x8a, 0x93, 0x27,
+            0x54, 0x02, 0x0e, 0x2c, 0xa8, 0x90, 0xa0, 0x41, 0x84, 0x23, 0x00];
+        let text = b"TOBEORNOTTOBEORTOBEORNOT#";
+        let mut buffer = vec![];
+        assert!(ZEncoder::new(&text[..], Z_MAX_BITS).encode(&mut buffer).is_ok());
+        assert_eq!(buffer, fixture);
+        let mut buffer_d = vec![];
+        assert!(ZDecoder::new(&fixture[..]).unwrap().decode(&mut buffer_d).is_ok());
+        assert_eq!(&buffer_d, text);
+
+        let text = noisy_text(200_000, b"abcdefghij klmnop\n");
+        for max_bits in [9, 12, 16]{
+            let mut buffer = vec![];
+            assert!(ZEncoder::new(&text[..], max_bits).encode(&mut buffer).is_ok());
+            let mut buffer_d = vec![];
+            assert!(ZDecoder::new(&buffer[..]).unwrap().decode(&mut buffer_d).is_ok());
+            assert_eq!(text, buffer_d);
+        }
+        assert!(matches!(ZDecoder::new(&[0x1f, 0x9d, 0x91][..]), Err(ZwlError::UnsupportedWidth(17))));
+    }
+
+    #[test]
+    fn gif_image_data(){
+        // Image data of the well known 1x1 transparent GIF, followed by the GIF trailer
+        let fixture = [0x02, 0x02, 0x44, 0x01, 0x00, 0x3B];
+        let mut input = &fixture[..];
+        let mut buffer_d = vec![];
+        assert!(GifDecoder::new(&mut input).unwrap().decode(&mut buffer_d).is_ok());
+        assert_eq!(buffer_d, [0]);
+        assert_eq!(input, [0x3B]);
+        let mut buffer = vec![];
+        assert!(GifEncoder::new(&[0u8][..], 2).encode(&mut buffer).is_ok());
+        assert_eq!(buffer, fixture[..5]);
+
+        for min_code_size in [2, 5, 8]{
+            let pixels: Vec<u8> = noisy_text(100_000, b"abcdefghij klmnop\n").iter().map(|byte| (u16::from(*byte) % (1 << min_code_size)) as u8).collect();
+            let mut buffer = vec![];
+            assert!(GifEncoder::new(&pixels[..], min_code_size).encode(&mut buffer).is_ok());
+            let mut buffer_d = vec![];
+            assert!(GifDecoder::new(&buffer[..]).unwrap().decode(&mut buffer_d).is_ok());
+            assert_eq!(pixels, buffer_d);
+        }
+        assert!(GifEncoder::new(&[4u8][..], 2).encode(io::sink()).is_err());
+    }
+
+
+    #[test]
+    fn tiff_lzw_early_change(){
+        // Example from the PDF reference: 256 45 258 258 65 259 66 257 packed MSB-first
+        let fixture = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+        let mut buffer = vec![];
+        assert!(TiffLzwEncoder::new(&b"-----A---B"[..]).encode(&mut buffer).is_ok());
+        assert_eq!(buffer, fixture);
+        let mut buffer_d = vec![];
+        assert!(TiffLzwDecoder::new(&fixture[..]).decode(&mut buffer_d).is_ok());
+        assert_eq!(buffer_d, b"-----A---B");
+
+        let text = noisy_text(200_000, b"abcdefghijklmnopqrstuvwxyz ,.\n");
+        for early_change in [true, false]{
+            let mut encoder = TiffLzwEncoder::new(&text[..]);
+            encoder.early_change = early_change;
+            let mut buffer = vec![];
+            assert!(encoder.encode(&mut buffer).is_ok());
+            let mut decoder = TiffLzwDecoder::new(&buffer[..]);
+            decoder.early_change = early_change;
+            let mut buffer_d = vec![];
+            assert!(decoder.decode(&mut buffer_d).is_ok());
+            assert_eq!(text, buffer_d);
+        }
+    }
+
+
+    #[test]
+    fn implicit_width_growth(){
+        let text = noisy_text(200_000, b"abcdefghijklmnopqrstuvwxyz ,.\n");
+        let mut implicit = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&text[..], FilledBehaviour::Freeze);
+        encoder.encode(&mut implicit).unwrap();
+        // Without the width escape the all-ones code is a regular entry too
+        assert_eq!(encoder.dictionary.len(), 1 << 12);
+        assert!(ZwlHeader::read_from(&mut &implicit[..]).unwrap().implicit_width);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&implicit[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+
+        let mut escaped = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&text[..], FilledBehaviour::Freeze);
+        encoder.dictionary.implicit_width = false;
+        encoder.encode(&mut escaped).unwrap();
+        assert!(!ZwlHeader::read_from(&mut &escaped[..]).unwrap().implicit_width);
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&escaped[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+        assert!(implicit.len() < escaped.len());
+    }
+
+
+    fn json_message(id: usize) -> Vec<u8>{
+        let status = ["ok", "warning", "error"][id % 3];
+        format!("{{\"id\":{id},\"sensor\":\"temp-{}\",\"value\":{}.{},\"status\":\"{status}\"}}", id % 7, 20 + id % 13, id % 10).into_bytes()
+    }
+
+    #[test]
+    fn preset_dictionary(){
+        let samples: Vec<u8> = (0..500).flat_map(json_message).collect();
+        let preset = PresetDictionary::train(&samples[..], 2000).unwrap();
+        assert!(!preset.phrases.is_empty() && preset.phrases.len() <= 2000);
+        let mut file = vec![];
+        preset.write_to(&mut file).unwrap();
+        assert_eq!(PresetDictionary::read_from(&mut &file[..]).unwrap(), preset);
+
+        let message = json_message(1234);
+        let mut plain = vec![];
+        ZwlBitEncoder::<LikeU16, _>::new(&message[..], FilledBehaviour::Clear).encode(&mut plain).unwrap();
+        let mut primed = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(&message[..], FilledBehaviour::Clear);
+        encoder.prime(&preset);
+        encoder.encode(&mut primed).unwrap();
+        assert!(primed.len() < plain.len());
+        assert_eq!(ZwlHeader::read_from(&mut &primed[..]).unwrap().preset_id, Some(preset.id()));
+
+        let options = DecodeOptions { preset: Some(preset.clone()), ..Default::default() };
+        let mut buffer_d = vec![];
+        assert!(get_decoder_with_options(&primed[..], &options).unwrap().1.decode(&mut buffer_d).is_ok());
+        assert_eq!(message, buffer_d);
+        assert!(matches!(get_decoder_with_options(&primed[..], &DecodeOptions::default()), Err(ZwlError::PresetMismatch { actual: None, .. })));
+        let other = DecodeOptions { preset: Some(PresetDictionary::new(vec![b"other".to_vec()])), ..Default::default() };
+        assert!(matches!(get_decoder_with_options(&primed[..], &other), Err(ZwlError::PresetMismatch { actual: Some(_), .. })));
+
+        // Clearing a full dictionary goes back to the preset phrases, with and without the width escape
+        let text = noisy_text(100_000, b"{}\":,abcdefghijklmnopqrstuvwxyz0123456789");
+        for implicit_width in [true, false]{
+            let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&text[..], FilledBehaviour::Clear);
+            encoder.dictionary.implicit_width = implicit_width;
+            encoder.prime(&preset);
+            let mut buffer = vec![];
+            encoder.encode(&mut buffer).unwrap();
+            let mut buffer_d = vec![];
+            assert!(get_decoder_with_options(&buffer[..], &options).unwrap().1.decode(&mut buffer_d).is_ok());
+            assert_eq!(text, buffer_d);
+        }
+    }
+
+
+    #[test]
+    fn block_mode_round_trips(){
+        let text = noisy_text(300_000, b"abcdefghijklmnopqrstuvwxyz ,.\n");
+        let mut buffer = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(&text[..], FilledBehaviour::Clear);
+        encoder.block_size = Some(40_000);
+        encoder.threads = 4;
+        encoder.encode(&mut buffer).unwrap();
+        assert_eq!(ZwlHeader::read_from(&mut &buffer[..]).unwrap().block_size, Some(40_000));
+        for threads in [1, 3]{
+            let options = DecodeOptions { threads, ..Default::default() };
+            let mut buffer_d = vec![];
+            assert!(get_decoder_with_options(&buffer[..], &options).unwrap().1.decode(&mut buffer_d).is_ok());
+            assert_eq!(text, buffer_d);
+        }
+        let mut buffer_d = vec![];
+        ZwlReader::new(&buffer[..]).unwrap().read_to_end(&mut buffer_d).unwrap();
+        assert_eq!(text, buffer_d);
+        // A block corrupted in the middle of the stream fails its checksum
+        let middle = buffer.len() / 2;
+        buffer[middle] ^= 0x10;
+        assert!(decode_auto(&buffer[..], io::sink()).is_err());
+
+        let mut buffer = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&[][..], FilledBehaviour::Freeze);
+        encoder.block_size = Some(1);
+        encoder.encode(&mut buffer).unwrap();
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert!(buffer_d.is_empty());
+    }
+
+
+    #[test]
+    fn seekable_reads_ranges(){
+        let text = noisy_text(250_000, b"abcdefghijklmnopqrstuvwxyz ,.\n");
+        let mut buffer = vec![];
+        let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(&text[..], FilledBehaviour::Clear);
+        encoder.block_size = Some(30_000);
+        encoder.seekable = true;
+        encoder.encode(&mut buffer).unwrap();
+        // Readers without seeking skip the index
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(text, buffer_d);
+
+        let mut reader = ZwlSeekableReader::new(io::Cursor::new(&buffer)).unwrap();
+        assert_eq!(reader.len(), text.len() as u64);
+        assert_eq!(reader.blocks.len(), 9);
+        for (start, len) in [(123_456, 1000), (29_990, 20), (0, 5), (249_990, 100), (260_000, 10)]{
+            let mut range = vec![];
+            reader.seek(io::SeekFrom::Start(start)).unwrap();
+            reader.by_ref().take(len).read_to_end(&mut range).unwrap();
+            let start = (start as usize).min(text.len());
+            assert_eq!(range, text[start..(start + len as usize).min(text.len())]);
+        }
+        assert_eq!(reader.seek(io::SeekFrom::End(-10)).unwrap(), text.len() as u64 - 10);
+        assert!(reader.seek(io::SeekFrom::Current(-300_000)).is_err());
+        let mut tail = vec![];
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, text[text.len() - 10..]);
+
+        let mut plain = vec![];
+        ZwlBitEncoder::<LikeU16, _>::new(&text[..], FilledBehaviour::Clear).encode(&mut plain).unwrap();
+        assert!(ZwlSeekableReader::new(io::Cursor::new(&plain)).is_err());
+    }
+
+
+    #[test]
+    fn concatenated_members(){
+        let parts = [noisy_text(3000, b"abcdef"), noisy_text(20_000, b"ghijklmnop \n"), vec![], noisy_text(50_000, b"qrstuvwxyz")];
+        let mut buffer = vec![];
+        ZwlBitEncoder::<LikeU12, _>::new(&parts[0][..], FilledBehaviour::Clear).encode(&mut buffer).unwrap();
+        let mut encoder = ZwlBitEncoder::<LikeU16, _>::new(&parts[1][..], FilledBehaviour::Freeze);
+        encoder.dictionary.implicit_width = false;
+        encoder.checksum = false;
+        encoder.encode(&mut buffer).unwrap();
+        ZwlBitEncoder::<LikeU12, _>::new(&parts[2][..], FilledBehaviour::Clear).encode(&mut buffer).unwrap();
+        let mut encoder = ZwlBitEncoder::<CodeWidth<14>, _>::new(&parts[3][..], FilledBehaviour::Lru);
+        encoder.block_size = Some(16_000);
+        encoder.seekable = true;
+        encoder.encode(&mut buffer).unwrap();
+        let joined = parts.concat();
+
+        let mut buffer_d = vec![];
+        assert!(decode_auto(&buffer[..], &mut buffer_d).is_ok());
+        assert_eq!(joined, buffer_d);
+        let mut buffer_d = vec![];
+        ZwlReader::new(&buffer[..]).unwrap().read_to_end(&mut buffer_d).unwrap();
+        assert_eq!(joined, buffer_d);
+        // A single member decoder still stops after the first member
+        let mut buffer_d = vec![];
+        assert!(get_decoder(&buffer[..]).unwrap().decode(&mut buffer_d).is_ok());
+        assert_eq!(parts[0], buffer_d);
+        // A broken header of a later member is an error
+        let first_len = {
+            let mut first = vec![];
+            ZwlBitEncoder::<LikeU12, _>::new(&parts[0][..], FilledBehaviour::Clear).encode(&mut first).unwrap();
+            first.len()
+        };
+        buffer[first_len + header::MAGIC.len()] = 0xFF;
+        assert!(decode_auto(&buffer[..], io::sink()).is_err());
+    }
+
+
+    #[test]
+    fn archive_round_trips(){
+        let text = noisy_text(40_000, b"abc de\n");
+        let mut archive = ZwlArchiveWriter::new(vec![]).unwrap();
+        archive.add_directory(ArchiveEntry::new("docs", EntryKind::Directory, 1_700_000_000, 0o755)).unwrap();
+        archive.add_file(ArchiveEntry::new("docs/preamble.txt", EntryKind::File, 1_700_000_001, 0o644), PREAMBLE.as_bytes()).unwrap();
+        archive.bit_size = 16;
+        archive.filled = FilledBehaviour::Lru;
+        archive.add_file(ArchiveEntry::new("noise.txt", EntryKind::File, 1_700_000_002, 0o600), &text[..]).unwrap();
+        archive.add_file(ArchiveEntry::new("empty", EntryKind::File, 0, 0o644), io::empty()).unwrap();
+        assert!(archive.add_file(ArchiveEntry::new("../escape", EntryKind::File, 0, 0o644), io::empty()).is_err());
+        assert!(archive.add_directory(ArchiveEntry::new("/root", EntryKind::Directory, 0, 0o755)).is_err());
+        let buffer = archive.finish().unwrap();
+
+        let mut archive = ZwlArchiveReader::new(io::Cursor::new(&buffer)).unwrap();
+        let paths: Vec<&str> = archive.entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, ["docs", "docs/preamble.txt", "noise.txt", "empty"]);
+        let noise = archive.find("noise.txt").unwrap();
+        assert_eq!(archive.entries[noise].size, text.len() as u64);
+        assert_eq!(archive.entries[noise].mode, 0o600);
+        assert_eq!(archive.entries[noise].mtime, 1_700_000_002);
+        // Entries decode in any order
+        let mut buffer_d = vec![];
+        archive.read_entry(noise, &mut buffer_d).unwrap();
+        assert_eq!(text, buffer_d);
+        let mut buffer_d = vec![];
+        archive.read_entry(archive.find("docs/preamble.txt").unwrap(), &mut buffer_d).unwrap();
+        assert_eq!(PREAMBLE.as_bytes(), buffer_d);
+        let mut buffer_d = vec![];
+        archive.read_entry(archive.find("empty").unwrap(), &mut buffer_d).unwrap();
+        assert!(buffer_d.is_empty());
+        assert!(matches!(archive.read_entry(archive.entries.len(), io::sink()), Err(ZwlError::Io(_))));
+
+        let mut broken = buffer.clone();
+        let len = broken.len();
+        broken[len - 1] = b'X';
+        assert!(ZwlArchiveReader::new(io::Cursor::new(&broken)).is_err());
+
+        // Entries can be block streams with a preset dictionary
+        let preset = PresetDictionary::train(&text[..], 500).unwrap();
+        let mut archive = ZwlArchiveWriter::new(vec![]).unwrap();
+        archive.preset = Some(preset.clone());
+        archive.block_size = Some(10_000);
+        archive.seekable = true;
+        archive.add_file(ArchiveEntry::new("noise.txt", EntryKind::File, 0, 0o644), &text[..]).unwrap();
+        let buffer = archive.finish().unwrap();
+        let mut archive = ZwlArchiveReader::new(io::Cursor::new(&buffer)).unwrap();
+        assert!(matches!(archive.read_entry(0, io::sink()), Err(ZwlError::PresetMismatch { .. })));
+        let options = DecodeOptions { preset: Some(preset), ..Default::default() };
+        let mut archive = ZwlArchiveReader::with_options(io::Cursor::new(&buffer), &options).unwrap();
+        let mut buffer_d = vec![];
+        archive.read_entry(0, &mut buffer_d).unwrap();
+        assert_eq!(text, buffer_d);
+    }
+
+
+    #[test]
+    fn encoder_and_decoder_stats_agree(){
+        let text = noisy_text(60_000, b"abcdefgh ");
+        for (implicit_width, filled, block_size) in [(true, FilledBehaviour::Clear, None), (false, FilledBehaviour::Clear, None), (false, FilledBehaviour::Freeze, Some(20_000))]{
+            let mut buffer = vec![];
+            let mut encoder = ZwlBitEncoder::<LikeU12, _>::new(&text[..], filled);
+            encoder.dictionary.implicit_width = implicit_width;
+            encoder.block_size = block_size;
+            encoder.encode(&mut buffer).unwrap();
+            let encoded = encoder.stats();
+            assert_eq!(encoded.input_bytes, text.len() as u64);
+            assert_eq!(encoded.output_bytes, buffer.len() as u64);
+            assert_eq!(encoded.width_escapes > 0, !implicit_width);
+            assert_eq!(encoded.clears > 0, block_size.is_none());
+            // Another run starts from zero, here on the drained input
+            let mut again = vec![];
+            encoder.encode(&mut again).unwrap();
+            assert_eq!(encoder.stats().input_bytes, 0);
+            assert_eq!(encoder.stats().output_bytes, again.len() as u64);
+
+            let mut decoder = ZwlMemberDecoder::new(&buffer[..], &DecodeOptions::default()).unwrap();
+            decoder.decode(io::sink()).unwrap();
+            let decoded = decoder.stats();
+            assert_eq!(decoded.output_bytes, text.len() as u64);
+            assert_eq!(decoded.codes, encoded.codes);
+            assert_eq!(decoded.width_escapes, encoded.width_escapes);
+            assert_eq!(decoded.clears, encoded.clears);
+            assert_eq!(decoded.dictionary_len, encoded.dictionary_len);
+        }
+    }
+
+
+    #[test]
+    fn read_errors_are_not_end_of_stream(){
+        struct Denied;
+        impl Read for Denied{
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+            }
+        }
+        let mut headerless = vec![];
+        assert!(ZwlBitEncoder::<LikeU12, _>::new_legacy(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode_headerless(&mut headerless).is_ok());
+        let input = headerless[..headerless.len() / 2].chain(Denied);
+        let mut decoder = ZwlBitDecoder::<LikeU12, _>::new_legacy(input, FilledBehaviour::Clear);
+        assert!(matches!(decoder.decode(io::sink()), Err(ZwlError::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied));
+    }
+
+
+    #[test]
+    fn preset_id_uses_whole_phrase_length(){
+        // With 16 bit lengths the long phrase would serialise to the same bytes as the two short ones
+        let tail = vec![b'z'; 65_534];
+        let long = PresetDictionary::new(vec![[&b"abc"[..], &[0xFE, 0xFF], &tail].concat()]);
+        let split = PresetDictionary::new(vec![b"abc".to_vec(), tail]);
+        assert_ne!(long.id(), split.id());
+    }
+
+
+    #[test]
+    fn trailing_data_with_part_of_the_magic(){
+        let mut buffer = vec![];
+        ZwlBitEncoder::<LikeU12, _>::new(PREAMBLE.as_bytes(), FilledBehaviour::Clear).encode(&mut buffer).unwrap();
+        for trailing in [&b"Zebra"[..], b"ZW", b"ZWL"]{
+            let mut joined = buffer.clone();
+            joined.extend_from_slice(trailing);
+            let mut buffer_d = vec![];
+            assert!(decode_auto(&joined[..], &mut buffer_d).is_ok());
+            assert_eq!(PREAMBLE.as_bytes(), &buffer_d[..]);
+            let mut decoder = ZwlMemberDecoder::new(&joined[..], &DecodeOptions::default()).unwrap();
+            assert!(decoder.decode(io::sink()).is_ok());
+            assert!(decoder.trailing_data);
+            assert_eq!(decoder.members, 1);
+            let mut rest = vec![];
+            decoder.into_trailing().unwrap().read_to_end(&mut rest).unwrap();
+            assert_eq!(trailing, &rest[..]);
+        }
+    }
+
+
+    #[test]
+    fn implicit_width_needs_clear_code(){
+        let text = noisy_text(60_000, b"abcdefghij \n");
+        // The encoder refuses to write streams that the decoder would misread
+        let mut encoder = ZwlBitEncoder::<CodeWidth<10>, _>::new_legacy(&text[..], FilledBehaviour::Clear);
+        encoder.dictionary.implicit_width = true;
+        assert!(matches!(encoder.encode(io::sink()), Err(ZwlError::BadHeader(_))));
+        // And the header reader refuses the flags, here set on a stream without implicit widths
+        let mut buffer = vec![];
+        ZwlBitEncoder::<CodeWidth<10>, _>::new_legacy(&text[..], FilledBehaviour::Clear).encode(&mut buffer).unwrap();
+        buffer[7] |= header::FLAG_IMPLICIT_WIDTH as u8;
+        assert!(matches!(decode_auto(&buffer[..], io::sink()), Err(ZwlError::BadHeader(_))));
+        // With the clear code the combination round trips at widths that fill up and clear
+        for bits in 10..=13{
+            let mut buffer = vec![];
+            let clears = with_code_width!(bits, W => {
+                let mut encoder = ZwlBitEncoder::<W, _>::new(&text[..], FilledBehaviour::Clear);
+                encoder.encode(&mut buffer).unwrap();
+                encoder.stats().clears
+            }, _ => unreachable!());
+            assert!(clears > 0);
+            let mut buffer_d = vec![];
+            decode_auto(&buffer[..], &mut buffer_d).unwrap();
+            assert_eq!(text, buffer_d);
+        }
+    }
 }
\ No newline at end of file
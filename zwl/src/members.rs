@@ -0,0 +1,123 @@
+use std::io::{Read, Write};
+
+use crate::{auto_decoder::{get_decoder_with_options, DecodeOptions, ZwlDecoderE}, error::ZwlError, header::{ZwlHeader, MAGIC}, stats::CodingStats};
+
+// Input of a member, starting with the bytes the previous member's decoder read past its end
+pub struct MemberInput<I: Read>{
+    prefix: Vec<u8>,
+    position: usize,
+    input: I,
+}
+
+impl<I: Read> MemberInput<I>{
+    pub fn new(input: I) -> Self{
+        Self { prefix: vec![], position: 0, input }
+    }
+    fn push_front(&mut self, bytes: Vec<u8>){
+        self.prefix.drain(..self.position);
+        self.prefix.splice(0..0, bytes);
+        self.position = 0;
+    }
+}
+
+impl<I: Read> Read for MemberInput<I>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.prefix.len(){
+            return self.input.read(buf);
+        }
+        let count = buf.len().min(self.prefix.len() - self.position);
+        buf[..count].copy_from_slice(&self.prefix[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+// Decodes concatenated members one after another, like gzip does. Members end at their end code
+// or block end marker, streams written without either run until the end of the input. Only
+// members with the current header can follow the first one.
+pub struct ZwlMemberDecoder<I: Read>{
+    decoder: Option<ZwlDecoderE<MemberInput<I>>>,
+    options: DecodeOptions,
+    // Header of the member that is being decoded
+    pub header: ZwlHeader,
+    pub members: usize,
+    // Set when data that is not another member follows the last one
+    pub trailing_data: bool,
+    buffer: Vec<u8>,
+    // Counters of the members that are already decoded
+    finished_stats: CodingStats,
+    // Input after the last member, starting with the trailing data
+    trailing: Option<MemberInput<I>>,
+}
+
+impl<I: Read> ZwlMemberDecoder<I>{
+    pub fn new(input: I, options: &DecodeOptions) -> Result<Self, ZwlError>{
+        let (header, decoder) = get_decoder_with_options(MemberInput::new(input), options)?;
+        Ok(Self{
+            decoder: Some(decoder),
+            options: options.clone(),
+            header,
+            members: 1,
+            trailing_data: false,
+            buffer: vec![],
+            finished_stats: CodingStats::default(),
+            trailing: None,
+        })
+    }
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        while let Some(decoder) = self.decoder.as_mut(){
+            decoder.decode(&mut output)?;
+            self.next_member()?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        while let Some(decoder) = self.decoder.as_mut(){
+            if let Some(sequence) = decoder.next_sequence()?{
+                self.buffer.clear();
+                self.buffer.extend_from_slice(sequence);
+                return Ok(Some(&self.buffer));
+            }
+            self.next_member()?;
+        }
+        Ok(None)
+    }
+    // Data after the last member, once decoding reached it
+    pub fn into_trailing(self) -> Option<MemberInput<I>>{
+        self.trailing
+    }
+    // Counters of all members so far
+    pub fn stats(&self) -> CodingStats{
+        let mut stats = self.finished_stats;
+        if let Some(decoder) = &self.decoder{
+            stats.merge(&decoder.stats());
+        }
+        stats
+    }
+    fn next_member(&mut self) -> Result<(), ZwlError>{
+        let Some(decoder) = self.decoder.take() else {
+            return Ok(());
+        };
+        self.finished_stats.merge(&decoder.stats());
+        let (rest, mut input) = decoder.into_input();
+        input.push_front(rest);
+        // Like gzip, data after the last member is ignored unless it starts another header
+        let mut magic = vec![];
+        (&mut input).take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+        if magic.is_empty(){
+            return Ok(());
+        }
+        input.push_front(magic.clone());
+        if magic != MAGIC{
+            self.trailing_data = true;
+            self.trailing = Some(input);
+            return Ok(());
+        }
+        let (header, decoder) = get_decoder_with_options(input, &self.options)?;
+        self.header = header;
+        self.decoder = Some(decoder);
+        self.members += 1;
+        Ok(())
+    }
+}
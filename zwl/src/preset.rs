@@ -0,0 +1,105 @@
+use std::{cmp::Reverse, io::{Read, Write}};
+
+use crate::{checksum::Crc32, code_width::LikeU32, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError};
+
+pub const PRESET_MAGIC: [u8; 4] = *b"ZWLD";
+// Words collected while training before the phrases are ranked
+const TRAIN_WORDS: usize = 1 << 20;
+
+// Phrases both sides add to their dictionaries before the first code, ordered so prefixes come first
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresetDictionary{
+    pub phrases: Vec<Vec<u8>>,
+}
+
+impl PresetDictionary{
+    pub fn new(phrases: Vec<Vec<u8>>) -> Self{
+        Self { phrases }
+    }
+    // Parses the samples like the encoder does and keeps the `max_phrases` most used phrases
+    pub fn train<R: Read>(mut input: R, max_phrases: usize) -> std::io::Result<Self>{
+        let mut dictionary = Dictionary::<LikeU32>::default();
+        dictionary.filled = FilledBehaviour::Freeze;
+        let first_word = dictionary.len();
+        let mut uses: Vec<u64> = vec![];
+        let mut index: Option<LikeU32> = None;
+        let mut buf = [0; 4096];
+        let mut s = input.read(&mut buf)?;
+        while s > 0{
+            for &byte in &buf[..s]{
+                let Some(prefix) = index else {
+                    index = Some(LikeU32::from(byte));
+                    continue;
+                };
+                if let Some(found) = dictionary.find_child(prefix, byte){
+                    index = Some(found);
+                    continue;
+                }
+                if let Some(position) = (prefix.0 as usize).checked_sub(first_word){
+                    uses[position] += 1;
+                }
+                if uses.len() < TRAIN_WORDS{
+                    dictionary.push(&(byte, prefix));
+                    uses.push(0);
+                }
+                index = Some(LikeU32::from(byte));
+            }
+            s = input.read(&mut buf)?;
+        }
+        if let Some(position) = index.and_then(|last| (last.0 as usize).checked_sub(first_word)){
+            uses[position] += 1;
+        }
+        // A phrase is used whenever a longer phrase starting with it is, so prefixes always rank higher
+        for position in (0..uses.len()).rev(){
+            if let Some(prefix) = dictionary.words[position].1.and_then(|prefix| (prefix.0 as usize).checked_sub(first_word)){
+                uses[prefix] += uses[position];
+            }
+        }
+        let mut ranked: Vec<usize> = (0..uses.len()).filter(|position| uses[*position] > 0).collect();
+        ranked.sort_by_key(|position| (Reverse(uses[*position]), *position));
+        ranked.truncate(max_phrases);
+        ranked.sort();
+        let phrases = ranked.into_iter()
+            .filter_map(|position| dictionary.get_phrase(LikeU32::try_from(first_word + position).ok()?))
+            .collect();
+        Ok(Self::new(phrases))
+    }
+    // CRC-32 of the phrases and their full lengths, stored in the header of streams that use this preset
+    pub fn id(&self) -> u32{
+        let mut crc = Crc32::new();
+        for phrase in &self.phrases{
+            crc.update(&(phrase.len() as u64).to_le_bytes());
+            crc.update(phrase);
+        }
+        crc.value()
+    }
+    pub fn write_to<O: Write>(&self, output: &mut O) -> std::io::Result<()>{
+        output.write_all(&PRESET_MAGIC)?;
+        let count = u32::try_from(self.phrases.len()).map_err(|_| std::io::Error::other("Preset dictionary has too many phrases"))?;
+        output.write_all(&count.to_le_bytes())?;
+        for phrase in &self.phrases{
+            let len = u16::try_from(phrase.len()).map_err(|_| std::io::Error::other("Preset phrase is too long"))?;
+            output.write_all(&len.to_le_bytes())?;
+            output.write_all(phrase)?;
+        }
+        output.flush()
+    }
+    pub fn read_from<I: Read>(input: &mut I) -> Result<Self, ZwlError>{
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if magic != PRESET_MAGIC{
+            return Err(ZwlError::BadHeader("Magic number of preset dictionary does not match".into()));
+        }
+        let mut count = [0; 4];
+        input.read_exact(&mut count)?;
+        let mut phrases = vec![];
+        for _ in 0..u32::from_le_bytes(count){
+            let mut len = [0; 2];
+            input.read_exact(&mut len)?;
+            let mut phrase = vec![0; u16::from_le_bytes(len).into()];
+            input.read_exact(&mut phrase)?;
+            phrases.push(phrase);
+        }
+        Ok(Self::new(phrases))
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{auto_decoder::{check_preset, DecodeOptions}, block::{decode_block, read_index_trailer, read_payload, FRAME_HEADER_LEN, INDEX_TRAILER_LEN}, error::ZwlError, header::ZwlHeader};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockEntry{
+    // Position of the block payload in the stream
+    pub compressed_offset: u64,
+    pub compressed_len: u32,
+    // Position of the decoded block in the original data
+    pub offset: u64,
+    pub len: u32,
+}
+
+// Reads a seekable stream and only decodes the blocks that are read from
+pub struct ZwlSeekableReader<R: Read + Seek>{
+    input: R,
+    pub header: ZwlHeader,
+    options: DecodeOptions,
+    pub blocks: Vec<BlockEntry>,
+    len: u64,
+    position: u64,
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> ZwlSeekableReader<R>{
+    pub fn new(input: R) -> Result<Self, ZwlError>{
+        Self::with_options(input, &DecodeOptions::default())
+    }
+    pub fn with_options(mut input: R, options: &DecodeOptions) -> Result<Self, ZwlError>{
+        let header = ZwlHeader::read_from(&mut input)?;
+        check_preset(&header, options)?;
+        if !header.seekable{
+            return Err(ZwlError::BadHeader("Stream was written without a block index".into()));
+        }
+        let mut compressed_offset = input.stream_position()? + FRAME_HEADER_LEN;
+        input.seek(SeekFrom::End(-(INDEX_TRAILER_LEN as i64)))?;
+        let mut trailer = [0; INDEX_TRAILER_LEN];
+        input.read_exact(&mut trailer)?;
+        let count = read_index_trailer(trailer)?;
+        input.seek(SeekFrom::End(-(INDEX_TRAILER_LEN as i64 + i64::from(count) * 8)))?;
+        let entries = read_payload(&mut input, count as usize * 8)?;
+        let mut blocks = Vec::with_capacity(count as usize);
+        let mut offset = 0;
+        for entry in entries.chunks_exact(8){
+            let compressed_len = u32::from_le_bytes(entry[..4].try_into().unwrap());
+            let len = u32::from_le_bytes(entry[4..].try_into().unwrap());
+            blocks.push(BlockEntry { compressed_offset, compressed_len, offset, len });
+            compressed_offset += u64::from(compressed_len) + FRAME_HEADER_LEN;
+            offset += u64::from(len);
+        }
+        Ok(Self{
+            input,
+            header,
+            options: options.clone(),
+            blocks,
+            len: offset,
+            position: 0,
+            cached: None,
+        })
+    }
+    // Length of the original data
+    pub fn len(&self) -> u64{
+        self.len
+    }
+    pub fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+    fn load_block(&mut self, block: usize) -> Result<(), ZwlError>{
+        if self.cached.as_ref().is_some_and(|(cached, _)| *cached == block){
+            return Ok(());
+        }
+        let entry = self.blocks[block];
+        self.input.seek(SeekFrom::Start(entry.compressed_offset))?;
+        let payload = read_payload(&mut self.input, entry.compressed_len as usize)?;
+        let (decoded, _) = decode_block(entry.len as usize, &payload, &self.header, &self.options)?;
+        self.cached = Some((block, decoded));
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for ZwlSeekableReader<R>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.len || buf.is_empty(){
+            return Ok(0);
+        }
+        let block = self.blocks.partition_point(|entry| entry.offset + u64::from(entry.len) <= self.position);
+        self.load_block(block)?;
+        let Some((_, decoded)) = &self.cached else {
+            return Ok(0);
+        };
+        let start = (self.position - self.blocks[block].offset) as usize;
+        let count = buf.len().min(decoded.len() - start);
+        buf[..count].copy_from_slice(&decoded[start..start + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for ZwlSeekableReader<R>{
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos{
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        let Some(position) = position else {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before the start of the data"));
+        };
+        self.position = position;
+        Ok(position)
+    }
+}
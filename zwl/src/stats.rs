@@ -0,0 +1,64 @@
+use std::io::{Read, Write};
+
+// Counters of one encoding or decoding run, input is what the coder reads and output what it writes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CodingStats{
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    // Codes in the stream, clear and end codes included
+    pub codes: u64,
+    // Only streams without implicit width growth have them
+    pub width_escapes: u64,
+    pub clears: u64,
+    // Entries of the dictionary at the end, reserved codes and the alphabet included
+    pub dictionary_len: usize,
+}
+
+impl CodingStats{
+    // Adds the counters of a later block or member, whose dictionary is the one left at the end
+    pub fn merge(&mut self, other: &CodingStats){
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+        self.codes += other.codes;
+        self.width_escapes += other.width_escapes;
+        self.clears += other.clears;
+        self.dictionary_len = other.dictionary_len;
+    }
+}
+
+// Counts the bytes that pass through in either direction
+pub struct Counter<T>{
+    inner: T,
+    count: u64,
+}
+
+impl<T> Counter<T>{
+    pub fn new(inner: T) -> Self{
+        Self { inner, count: 0 }
+    }
+    pub fn count(&self) -> u64{
+        self.count
+    }
+    pub fn into_inner(self) -> T{
+        self.inner
+    }
+}
+
+impl<T: Read> Read for Counter<T>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.count += count as u64;
+        Ok(count)
+    }
+}
+
+impl<T: Write> Write for Counter<T>{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.count += count as u64;
+        Ok(count)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
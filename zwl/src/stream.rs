@@ -0,0 +1,113 @@
+use std::{io::{Empty, Read, Write}, ops::Sub};
+
+use crate::{bit_io::BitWriter, auto_decoder::DecodeOptions, members::ZwlMemberDecoder, bit_encoder::ZwlBitEncoder, dictionary::FilledBehaviour, error::ZwlError, traits::{CustomWriteSize, LeadingZerosR, RequiredBits, TrailingOnesR}};
+
+pub struct ZwlWriter<T, W: Write>
+where 
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + CustomWriteSize
+    + LeadingZerosR + TrailingOnesR + RequiredBits{
+    encoder: ZwlBitEncoder<T, Empty>,
+    writtable: Option<BitWriter<W>>,
+}
+
+impl<T, W: Write> ZwlWriter<T, W>
+where 
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + CustomWriteSize
+    + LeadingZerosR + TrailingOnesR + RequiredBits{
+    pub fn new(mut output: W, dictionary_filled: FilledBehaviour) -> Result<Self, ZwlError>{
+        let encoder = ZwlBitEncoder::new(std::io::empty(), dictionary_filled);
+        encoder.write_header(&mut output)?;
+        Ok(Self{
+            encoder,
+            writtable: Some(BitWriter::new(output)),
+        })
+    }
+    pub fn clear_dictionary(&mut self) -> Result<(), ZwlError>{
+        match self.writtable.as_mut(){
+            Some(writtable) => self.encoder.clear_dictionary(writtable),
+            None => Err(std::io::Error::other("ZwlWriter was already finished").into()),
+        }
+    }
+    pub fn finish(mut self) -> Result<(), ZwlError>{
+        self.finish_stream()
+    }
+    fn finish_stream(&mut self) -> Result<(), ZwlError>{
+        if let Some(mut writtable) = self.writtable.take(){
+            self.encoder.finish_codes(&mut writtable)?;
+            self.encoder.write_trailer(&mut writtable)?;
+            writtable.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, W: Write> Write for ZwlWriter<T, W>
+where 
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + CustomWriteSize
+    + LeadingZerosR + TrailingOnesR + RequiredBits{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let Some(writtable) = self.writtable.as_mut() else {
+            return Err(std::io::Error::other("ZwlWriter was already finished"));
+        };
+        self.encoder.push_bytes(buf, writtable)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.writtable.as_mut(){
+            Some(writtable) => writtable.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, W: Write> Drop for ZwlWriter<T, W>
+where 
+    T: TryInto<usize, Error: std::fmt::Debug> + TryFrom<usize, Error: std::fmt::Debug> + From<u8> + std::fmt::Debug + PartialOrd + Copy + Sub<T, Output = T> + min_max_traits::Max + CustomWriteSize
+    + LeadingZerosR + TrailingOnesR + RequiredBits{
+    fn drop(&mut self) {
+        let _ = self.finish_stream();
+    }
+}
+
+
+pub struct ZwlReader<R: Read>{
+    decoder: ZwlMemberDecoder<R>,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> ZwlReader<R>{
+    pub fn new(input: R) -> Result<Self, ZwlError>{
+        Self::with_options(input, &DecodeOptions::default())
+    }
+    pub fn with_options(input: R, options: &DecodeOptions) -> Result<Self, ZwlError>{
+        Ok(Self{
+            decoder: ZwlMemberDecoder::new(input, options)?,
+            buffer: vec![],
+            position: 0,
+            finished: false,
+        })
+    }
+}
+
+impl<R: Read> Read for ZwlReader<R>{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.buffer.len() && !self.finished{
+            match self.decoder.next_sequence()?{
+                Some(sequence) => {
+                    self.buffer.clear();
+                    self.buffer.extend_from_slice(sequence);
+                    self.position = 0;
+                },
+                None => self.finished = true,
+            }
+        }
+        let available = &self.buffer[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
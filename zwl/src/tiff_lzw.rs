@@ -0,0 +1,180 @@
+use std::io::{Read, Write};
+
+use crate::{bit_io::{MsbBitReader, MsbBitWriter}, code_width::LikeU16, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError};
+
+// TIFF compression 5 and PDF LZWDecode: MSB-first codes of 9 to 12 bits, CLEAR is 256 and EOD is 257
+pub const TIFF_MIN_BITS: usize = 9;
+pub const TIFF_MAX_BITS: usize = 12;
+const CLEAR: u64 = 256;
+const EOD: u64 = 257;
+// Encoders clear the table before its last two codes are used, like libtiff does
+const CLEAR_AT: usize = (1 << TIFF_MAX_BITS) - 2;
+
+fn new_dictionary() -> Dictionary<LikeU16>{
+    let mut dictionary = Dictionary::default();
+    dictionary.filled = FilledBehaviour::Freeze;
+    dictionary.reserved = 2;
+    dictionary
+}
+
+// With early change the width grows one code before the next code would need it
+fn should_grow(next_code: usize, bits: usize, early_change: bool) -> bool{
+    bits < TIFF_MAX_BITS && next_code + usize::from(early_change) >= 1 << bits
+}
+
+pub struct TiffLzwEncoder<I: Read>{
+    input: I,
+    pub early_change: bool,
+}
+
+impl<I: Read> TiffLzwEncoder<I>{
+    pub fn new(input: I) -> Self{
+        Self { input, early_change: true }
+    }
+    pub fn encode<O: Write>(&mut self, output: O) -> Result<(), ZwlError> {
+        let mut state = TiffEncoderState{
+            writtable: MsbBitWriter::new(output),
+            dictionary: new_dictionary(),
+            bits: TIFF_MIN_BITS,
+            early_change: self.early_change,
+        };
+        state.writtable.write_bits(CLEAR, state.bits as u32)?;
+        let mut index: Option<LikeU16> = None;
+        let mut buf = [0; 4096];
+        let mut s = self.input.read(&mut buf)?;
+        while s > 0{
+            for &byte in &buf[..s]{
+                let Some(prefix) = index else {
+                    index = Some(LikeU16::from(byte));
+                    continue;
+                };
+                if let Some(found) = state.dictionary.find_child(prefix, byte){
+                    index = Some(found);
+                    continue;
+                }
+                state.write_code(prefix.0)?;
+                state.dictionary.push(&(byte, prefix));
+                state.added(state.dictionary.len())?;
+                index = Some(LikeU16::from(byte));
+            }
+            s = self.input.read(&mut buf)?;
+        }
+        if let Some(last) = index{
+            state.write_code(last.0)?;
+            // The decoder adds one more entry when it reads the last code
+            state.added(state.dictionary.len() + 1)?;
+        }
+        state.write_code(EOD)?;
+        state.writtable.finish()?;
+        Ok(())
+    }
+}
+
+struct TiffEncoderState<W: Write>{
+    writtable: MsbBitWriter<W>,
+    dictionary: Dictionary<LikeU16>,
+    bits: usize,
+    early_change: bool,
+}
+
+impl<W: Write> TiffEncoderState<W>{
+    fn write_code(&mut self, code: u64) -> std::io::Result<()>{
+        self.writtable.write_bits(code, self.bits as u32)
+    }
+    // The encoder is one entry ahead of the decoder, which decides the width of the next code
+    fn added(&mut self, next_code: usize) -> std::io::Result<()>{
+        if next_code == CLEAR_AT{
+            self.write_code(CLEAR)?;
+            self.dictionary.clear();
+            self.bits = TIFF_MIN_BITS;
+        }
+        else if should_grow(next_code - 1, self.bits, self.early_change){
+            self.bits += 1;
+        }
+        Ok(())
+    }
+}
+
+pub struct TiffLzwDecoder<I: Read>{
+    input: MsbBitReader<I>,
+    pub early_change: bool,
+    dictionary: Dictionary<LikeU16>,
+    bits: usize,
+    old_index: Option<LikeU16>,
+    old_sequence: Vec<u8>,
+    finished: bool,
+}
+
+impl<I: Read> TiffLzwDecoder<I>{
+    pub fn new(input: I) -> Self{
+        Self{
+            input: MsbBitReader::new(input),
+            early_change: true,
+            dictionary: new_dictionary(),
+            bits: TIFF_MIN_BITS,
+            old_index: None,
+            old_sequence: vec![],
+            finished: false,
+        }
+    }
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        while let Some(sequence) = self.next_sequence()?{
+            output.write_all(sequence)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        loop{
+            if self.finished{
+                return Ok(None);
+            }
+            let code = match self.input.read_bits(self.bits as u32){
+                Ok(code) => code,
+                // Streams cut at a strip boundary may leave out EOD
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.finished = true;
+                    return Ok(None);
+                },
+                Err(e) => return Err(e.into()),
+            };
+            if code == CLEAR{
+                self.dictionary.clear();
+                self.bits = TIFF_MIN_BITS;
+                self.old_index = None;
+                continue;
+            }
+            if code == EOD{
+                self.finished = true;
+                return Ok(None);
+            }
+            let code = code as usize;
+            let index = LikeU16::try_from(code).map_err(|_| ZwlError::InvalidCode(code))?;
+            let Some(old_index) = self.old_index else {
+                if code >= self.dictionary.alphabet.len(){
+                    return Err(ZwlError::InvalidCode(code));
+                }
+                self.old_index = Some(index);
+                self.old_sequence = vec![code as u8];
+                return Ok(Some(&self.old_sequence));
+            };
+            let sequence = if code == self.dictionary.len() && code < 1 << TIFF_MAX_BITS{
+                let mut sequence = self.old_sequence.clone();
+                sequence.push(self.old_sequence[0]);
+                sequence
+            }
+            else{
+                self.dictionary.get_phrase(index).ok_or(ZwlError::InvalidCode(code))?
+            };
+            if self.dictionary.len() < 1 << TIFF_MAX_BITS{
+                self.dictionary.push(&(sequence[0], old_index));
+                if should_grow(self.dictionary.len(), self.bits, self.early_change){
+                    self.bits += 1;
+                }
+            }
+            self.old_index = Some(index);
+            self.old_sequence = sequence;
+            return Ok(Some(&self.old_sequence));
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::{Read, Write};
+
+use crate::{bit_io::{BitReader, BitWriter}, code_width::LikeU32, dictionary::{Dictionary, FilledBehaviour}, error::ZwlError};
+
+// Container of compress(1) and ncompress: magic, then max bits with the block mode flag
+pub const Z_MAGIC: [u8; 2] = [0x1F, 0x9D];
+pub const Z_MIN_BITS: u8 = 9;
+pub const Z_MAX_BITS: u8 = 16;
+const BIT_MASK: u8 = 0x1F;
+const BLOCK_MODE: u8 = 0x80;
+const CLEAR: u64 = 256;
+// Codes are written in groups of eight, a group is padded when the code width changes
+const GROUP_CODES: usize = 8;
+const CHECK_GAP: u64 = 10_000;
+
+// compress only allows the full table once it grew to max bits, so with 9 max bits the
+// table still overflows into 10 bit codes like it does in ncompress and gzip
+fn max_code(bits: usize, max_bits: usize) -> usize{
+    if bits == max_bits && bits > Z_MIN_BITS as usize { 1 << max_bits } else { (1 << bits) - 1 }
+}
+
+fn new_dictionary(block_mode: bool) -> Dictionary<LikeU32>{
+    let mut dictionary = Dictionary::default();
+    dictionary.filled = FilledBehaviour::Freeze;
+    dictionary.reserved = usize::from(block_mode);
+    dictionary
+}
+
+pub struct ZEncoder<I: Read>{
+    input: I,
+    pub max_bits: u8,
+    pub block_mode: bool,
+}
+
+impl<I: Read> ZEncoder<I>{
+    pub fn new(input: I, max_bits: u8) -> Self{
+        Self { input, max_bits, block_mode: true }
+    }
+    pub fn encode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        if !(Z_MIN_BITS..=Z_MAX_BITS).contains(&self.max_bits){
+            return Err(ZwlError::UnsupportedWidth(self.max_bits));
+        }
+        let mode = if self.block_mode { BLOCK_MODE } else { 0 };
+        output.write_all(&Z_MAGIC)?;
+        output.write_all(&[self.max_bits | mode])?;
+        let mut state = ZEncoderState{
+            writtable: BitWriter::new(&mut output),
+            dictionary: new_dictionary(self.block_mode),
+            bits: Z_MIN_BITS as usize,
+            max_bits: self.max_bits as usize,
+            group_codes: 0,
+            clear_flag: false,
+            written_bits: 0,
+        };
+        let max_entries = 1 << self.max_bits;
+        let mut index: Option<LikeU32> = None;
+        let mut read_bytes = 0u64;
+        let mut checkpoint = CHECK_GAP;
+        let mut ratio = 0;
+        let mut buf = [0; 4096];
+        let mut s = self.input.read(&mut buf)?;
+        while s > 0{
+            for &byte in &buf[..s]{
+                read_bytes += 1;
+                let Some(prefix) = index else {
+                    index = Some(LikeU32::from(byte));
+                    continue;
+                };
+                if let Some(found) = state.dictionary.find_child(prefix, byte){
+                    index = Some(found);
+                    continue;
+                }
+                state.write_code(prefix.0)?;
+                if state.dictionary.len() < max_entries{
+                    state.dictionary.push(&(byte, prefix));
+                }
+                else if self.block_mode && read_bytes >= checkpoint{
+                    checkpoint = read_bytes + CHECK_GAP;
+                    let new_ratio = (read_bytes << 8) / state.written_bits.max(1);
+                    if new_ratio > ratio{
+                        ratio = new_ratio;
+                    }
+                    else{
+                        ratio = 0;
+                        state.dictionary.clear();
+                        state.clear_flag = true;
+                        state.write_code(CLEAR)?;
+                    }
+                }
+                index = Some(LikeU32::from(byte));
+            }
+            s = self.input.read(&mut buf)?;
+        }
+        if let Some(last) = index{
+            state.write_code(last.0)?;
+        }
+        state.writtable.finish()?;
+        Ok(())
+    }
+}
+
+struct ZEncoderState<W: Write>{
+    writtable: BitWriter<W>,
+    dictionary: Dictionary<LikeU32>,
+    bits: usize,
+    max_bits: usize,
+    group_codes: usize,
+    clear_flag: bool,
+    written_bits: u64,
+}
+
+impl<W: Write> ZEncoderState<W>{
+    fn write_code(&mut self, code: u64) -> std::io::Result<()>{
+        self.write_bits(code, self.bits)?;
+        self.group_codes = (self.group_codes + 1) % GROUP_CODES;
+        if self.clear_flag || self.dictionary.len() > max_code(self.bits, self.max_bits){
+            if self.group_codes > 0{
+                self.write_bits(0, (GROUP_CODES - self.group_codes) * self.bits)?;
+                self.group_codes = 0;
+            }
+            if self.clear_flag{
+                self.bits = Z_MIN_BITS as usize;
+                self.clear_flag = false;
+            }
+            else{
+                self.bits += 1;
+            }
+        }
+        Ok(())
+    }
+    fn write_bits(&mut self, value: u64, mut count: usize) -> std::io::Result<()>{
+        self.written_bits += count as u64;
+        while count > 64{
+            self.writtable.write_bits(0, 64)?;
+            count -= 64;
+        }
+        self.writtable.write_bits(value, count as u32)
+    }
+}
+
+pub struct ZDecoder<I: Read>{
+    input: BitReader<I>,
+    pub max_bits: u8,
+    pub block_mode: bool,
+    dictionary: Dictionary<LikeU32>,
+    bits: usize,
+    group_codes: usize,
+    clear_flag: bool,
+    old_index: Option<LikeU32>,
+    old_sequence: Vec<u8>,
+}
+
+impl<I: Read> ZDecoder<I>{
+    pub fn new(mut input: I) -> Result<Self, ZwlError>{
+        let mut header = [0; 3];
+        input.read_exact(&mut header)?;
+        if header[..2] != Z_MAGIC{
+            return Err(ZwlError::BadHeader("Magic number of .Z file does not match".into()));
+        }
+        let max_bits = header[2] & BIT_MASK;
+        if header[2] & !(BIT_MASK | BLOCK_MODE) != 0{
+            return Err(ZwlError::BadHeader(format!("Unknown .Z flags {:#04x}", header[2])));
+        }
+        if !(Z_MIN_BITS..=Z_MAX_BITS).contains(&max_bits){
+            return Err(ZwlError::UnsupportedWidth(max_bits));
+        }
+        let block_mode = header[2] & BLOCK_MODE != 0;
+        Ok(Self{
+            input: BitReader::new(input),
+            max_bits,
+            block_mode,
+            dictionary: new_dictionary(block_mode),
+            bits: Z_MIN_BITS as usize,
+            group_codes: 0,
+            clear_flag: false,
+            old_index: None,
+            old_sequence: vec![],
+        })
+    }
+    pub fn decode<O: Write>(&mut self, mut output: O) -> Result<(), ZwlError> {
+        while let Some(sequence) = self.next_sequence()?{
+            output.write_all(sequence)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    pub fn next_sequence(&mut self) -> Result<Option<&[u8]>, ZwlError> {
+        loop{
+            let Some(code) = self.read_code()? else {
+                return Ok(None);
+            };
+            if self.block_mode && code == CLEAR{
+                self.dictionary.clear();
+                self.clear_flag = true;
+                self.old_index = None;
+                continue;
+            }
+            let index = usize::try_from(code).ok().and_then(|code| LikeU32::try_from(code).ok()).ok_or(ZwlError::InvalidCode(usize::MAX))?;
+            let code = code as usize;
+            let Some(old_index) = self.old_index else {
+                if code > u8::MAX as usize{
+                    return Err(ZwlError::InvalidCode(code));
+                }
+                self.old_index = Some(index);
+                self.old_sequence = vec![code as u8];
+                return Ok(Some(&self.old_sequence));
+            };
+            let sequence = if code == self.dictionary.len(){
+                let mut sequence = self.old_sequence.clone();
+                sequence.push(self.old_sequence[0]);
+                sequence
+            }
+            else{
+                self.dictionary.get_phrase(index).ok_or(ZwlError::InvalidCode(code))?
+            };
+            if self.dictionary.len() < 1 << self.max_bits{
+                self.dictionary.push(&(sequence[0], old_index));
+            }
+            self.old_index = Some(index);
+            self.old_sequence = sequence;
+            return Ok(Some(&self.old_sequence));
+        }
+    }
+    fn read_code(&mut self) -> Result<Option<u64>, ZwlError>{
+        let max_bits = self.max_bits as usize;
+        if self.clear_flag || self.dictionary.len() > max_code(self.bits, max_bits){
+            if self.group_codes > 0{
+                let mut skipped = (GROUP_CODES - self.group_codes) * self.bits;
+                while skipped > 0{
+                    let count = skipped.min(64);
+                    if self.read_bits(count)?.is_none(){
+                        return Ok(None);
+                    }
+                    skipped -= count;
+                }
+                self.group_codes = 0;
+            }
+            if self.clear_flag{
+                self.bits = Z_MIN_BITS as usize;
+                self.clear_flag = false;
+            }
+            else{
+                self.bits += 1;
+            }
+        }
+        let code = self.read_bits(self.bits)?;
+        self.group_codes = (self.group_codes + 1) % GROUP_CODES;
+        Ok(code)
+    }
+    fn read_bits(&mut self, count: usize) -> Result<Option<u64>, ZwlError>{
+        match self.input.read_bits(count as u32){
+            Ok(bits) => Ok(Some(bits)),
+            // compress pads the last code to a byte, which never holds another whole code
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}